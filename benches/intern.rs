@@ -0,0 +1,36 @@
+//! Times interning from one thread, both strings that are already interned
+//! and new ones.
+//!
+//! ```sh
+//! cargo bench --bench intern
+//! ```
+
+use std::{hint::black_box, time::Instant};
+use str_id::StrID;
+
+/// How many distinct strings are interned.
+const STRINGS: usize = 100_000;
+
+/// How many times each string is interned again after it's new.
+const ROUNDS: usize = 50;
+
+fn main() {
+  let strings: Vec<String> =
+    (0..STRINGS).map(|i| format!("intern bench {i}")).collect();
+
+  let start = Instant::now();
+  for s in &strings {
+    black_box(StrID::from(black_box(s.as_str())));
+  }
+  let per_miss = start.elapsed().as_nanos() as f64 / STRINGS as f64;
+  println!("new strings: {per_miss:.1}ns per intern");
+
+  let start = Instant::now();
+  for _ in 0..ROUNDS {
+    for s in &strings {
+      black_box(StrID::from(black_box(s.as_str())));
+    }
+  }
+  let per_hit = start.elapsed().as_nanos() as f64 / (ROUNDS * STRINGS) as f64;
+  println!("interned strings: {per_hit:.1}ns per intern");
+}
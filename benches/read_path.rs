@@ -0,0 +1,45 @@
+//! Times looking up the string of an ID, from one thread and from several.
+//!
+//! Run this with and without the `arc-swap` feature to compare the lock free
+//! table with the cache's lock:
+//!
+//! ```sh
+//! cargo bench --bench read_path
+//! cargo bench --bench read_path --features arc-swap
+//! ```
+
+use std::{hint::black_box, thread, time::Instant};
+use str_id::StrID;
+
+/// How many distinct strings are interned before the lookups start.
+const STRINGS: usize = 10_000;
+
+/// How many lookups each thread does.
+const LOOKUPS: usize = 10_000_000;
+
+fn main() {
+  let ids: Vec<StrID> = (0..STRINGS)
+    .map(|i| StrID::from(format!("read path {i}").as_str()))
+    .collect();
+  let locking = if cfg!(feature = "arc-swap") { "arc-swap" } else { "lock" };
+  for threads in [1, 2, 4, 8] {
+    let start = Instant::now();
+    thread::scope(|scope| {
+      for t in 0..threads {
+        let ids = &ids;
+        scope.spawn(move || {
+          let mut total = 0;
+          for i in 0..LOOKUPS {
+            total += black_box(ids[(i * 7 + t) % STRINGS]).as_str().len();
+          }
+          black_box(total);
+        });
+      }
+    });
+    let elapsed = start.elapsed();
+    let per_lookup = elapsed.as_nanos() as f64 / (threads * LOOKUPS) as f64;
+    println!(
+      "{locking}: {threads} thread(s), {per_lookup:.1}ns per lookup ({elapsed:.2?} total)"
+    );
+  }
+}
@@ -0,0 +1,49 @@
+//! `arbitrary` and `proptest` support, generating interned strings.
+//!
+//! Every generated ID is interned, and the cache is never purged, so the
+//! generated strings are kept short to limit how much a long fuzzing run can
+//! grow the cache.
+
+use crate::StrID;
+
+/// The longest string (in bytes) that gets generated.
+const MAX_LEN: usize = 32;
+
+/// Cuts a string down to at most `MAX_LEN` bytes, on a char boundary.
+#[inline]
+fn truncate(s: &str) -> &str {
+  let mut end = s.len().min(MAX_LEN);
+  while !s.is_char_boundary(end) {
+    end -= 1;
+  }
+  &s[..end]
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for StrID {
+  #[inline]
+  fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+    let s: &str = u.arbitrary()?;
+    Ok(StrID::from(truncate(s)))
+  }
+
+  #[inline]
+  fn size_hint(depth: usize) -> (usize, Option<usize>) {
+    <&str as arbitrary::Arbitrary>::size_hint(depth)
+  }
+}
+
+#[cfg(feature = "proptest")]
+impl proptest::arbitrary::Arbitrary for StrID {
+  type Parameters = ();
+  type Strategy = proptest::strategy::BoxedStrategy<StrID>;
+
+  #[inline]
+  fn arbitrary_with((): Self::Parameters) -> Self::Strategy {
+    use proptest::strategy::Strategy;
+    // up to 32 non-control chars, which might be more than `MAX_LEN` bytes.
+    r"\PC{0,32}"
+      .prop_map(|s: alloc::string::String| StrID::from(truncate(&s)))
+      .boxed()
+  }
+}
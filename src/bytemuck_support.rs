@@ -0,0 +1,14 @@
+//! bytemuck support, so that a [StrID] can be viewed as bytes.
+//!
+//! Only [NoUninit] is implemented. A `StrID` can never be zero, so it isn't
+//! `Zeroable` (and so it isn't `Pod` either), and not every bit pattern is a
+//! valid ID.
+#![allow(unsafe_code)]
+
+use crate::StrID;
+use bytemuck::NoUninit;
+
+// Safety: `StrID` is `repr(transparent)` over a `NonZero` integer, so it has
+// no padding and every byte of it is always initialized. It doesn't have any
+// interior mutability either.
+unsafe impl NoUninit for StrID {}
@@ -0,0 +1,135 @@
+//! One-shot setup of the global cache.
+
+use crate::{
+  DEFAULT_SHARD_COUNT, MAX_SHARD_COUNT, hasher::HasherKind, init_cache,
+};
+use core::fmt;
+
+/// Settings for the cache, applied before it's first used.
+///
+/// ```
+/// # use str_id::{shard_count, InternConfig, StrID};
+/// let config = InternConfig::new().shards(4).hasher_std().capacity(1024);
+/// assert!(config.init().is_ok());
+/// assert_eq!(shard_count(), 4);
+/// assert_eq!(StrID::from("foo"), "foo");
+/// assert!(config.init().is_err());
+/// ```
+///
+/// **The configuration is one-shot.** The cache is set up the first time that
+/// anything uses it, so once a string has been interned (or the cache has been
+/// set up some other way) [init](Self::init) does nothing and gives
+/// [ConfigError::AlreadyInitialized].
+///
+/// The shard count is part of every ID value, so the IDs of one run of the
+/// program only make sense with the same shard count (which matters for
+/// [import_snapshot](crate::import_snapshot)). The hashers that can be picked
+/// are the ones that are enabled by cargo features (see the crate docs on
+/// hashing), plus the standard one, which is always available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InternConfig {
+  shards: usize,
+  capacity: usize,
+  hasher: HasherKind,
+}
+impl InternConfig {
+  /// Makes a config with the default settings: [DEFAULT_SHARD_COUNT] shards,
+  /// the default hasher, and no capacity reserved up front.
+  #[inline]
+  #[must_use]
+  pub fn new() -> Self {
+    Self {
+      shards: DEFAULT_SHARD_COUNT,
+      capacity: 0,
+      hasher: HasherKind::default(),
+    }
+  }
+
+  /// Sets the number of shards to split the cache into.
+  ///
+  /// This must be from 1 to [MAX_SHARD_COUNT]. More shards let more threads
+  /// intern new strings at once, at the cost of a little memory per shard.
+  #[inline]
+  #[must_use]
+  pub const fn shards(self, shards: usize) -> Self {
+    Self { shards, ..self }
+  }
+
+  /// Sets how many strings the cache has room for when it's set up, just like
+  /// [init_with_capacity](crate::init_with_capacity).
+  #[inline]
+  #[must_use]
+  pub const fn capacity(self, capacity: usize) -> Self {
+    Self { capacity, ..self }
+  }
+
+  /// Makes the cache hash with `fnv`.
+  #[cfg(feature = "fnv")]
+  #[inline]
+  #[must_use]
+  pub const fn hasher_fnv(self) -> Self {
+    Self { hasher: HasherKind::Fnv, ..self }
+  }
+
+  /// Makes the cache hash with `ahash`.
+  #[cfg(feature = "ahash")]
+  #[inline]
+  #[must_use]
+  pub const fn hasher_ahash(self) -> Self {
+    Self { hasher: HasherKind::Ahash, ..self }
+  }
+
+  /// Makes the cache hash with the standard library's default hasher (or the
+  /// default hasher of `hashbrown`, without `std`).
+  #[inline]
+  #[must_use]
+  pub const fn hasher_std(self) -> Self {
+    Self { hasher: HasherKind::Std, ..self }
+  }
+
+  /// Sets up the cache with this config.
+  ///
+  /// Nothing is changed on an error.
+  #[inline]
+  pub fn init(self) -> Result<(), ConfigError> {
+    if !(1..=MAX_SHARD_COUNT).contains(&self.shards) {
+      return Err(ConfigError::UnsupportedShardCount(self.shards));
+    }
+    if init_cache(self.shards, self.capacity, self.hasher) {
+      Ok(())
+    } else {
+      Err(ConfigError::AlreadyInitialized)
+    }
+  }
+}
+
+impl Default for InternConfig {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// An error from [InternConfig::init].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ConfigError {
+  /// The cache was already set up, so the config can't apply.
+  AlreadyInitialized,
+  /// The config asked for 0 shards, or more than [MAX_SHARD_COUNT].
+  UnsupportedShardCount(usize),
+}
+
+impl fmt::Display for ConfigError {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::AlreadyInitialized => f.write_str("the cache is already set up"),
+      Self::UnsupportedShardCount(n) => {
+        write!(f, "the cache can't have {n} shards")
+      }
+    }
+  }
+}
+
+impl core::error::Error for ConfigError {}
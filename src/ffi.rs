@@ -0,0 +1,62 @@
+//! A C interface to the global cache.
+//!
+//! These functions share the same cache as the Rust API, so an ID interned
+//! from C is the same ID that Rust code gets for that string, and the other
+//! way around. IDs cross the boundary as their raw `usize` value, with 0
+//! meaning "no ID".
+#![allow(unsafe_code)]
+
+use crate::StrID;
+
+/// Interns `len` bytes starting at `ptr`, giving back the raw ID value.
+///
+/// Gives 0 if `ptr` is null, if the bytes aren't valid UTF-8, or if the ID
+/// values have run out. A non-null `ptr` with a `len` of 0 interns the empty
+/// string.
+///
+/// ## Safety
+/// * If `ptr` isn't null, it must be valid for reading `len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn str_id_intern(ptr: *const u8, len: usize) -> usize {
+  if ptr.is_null() {
+    return 0;
+  }
+  // Safety: the caller promises that `ptr` is valid for `len` bytes.
+  let bytes = unsafe { core::slice::from_raw_parts(ptr, len) };
+  match core::str::from_utf8(bytes) {
+    Ok(s) => StrID::try_from_str(s).map_or(0, StrID::as_usize),
+    Err(_) => 0,
+  }
+}
+
+/// Gets a pointer to the string of a raw ID value, writing the string's length
+/// in bytes to `out_len`.
+///
+/// The string data is never moved or freed, so the pointer stays valid for the
+/// rest of the program. The data is UTF-8, and it is *not* null terminated.
+///
+/// If the ID isn't in the cache (including 0), this gives a null pointer and
+/// writes a length of 0. The empty string gives a non-null pointer with a
+/// length of 0. If `out_len` is null, no length is written.
+///
+/// ## Safety
+/// * If `out_len` isn't null, it must be valid for writing a `usize`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn str_id_as_str(
+  id: usize, out_len: *mut usize,
+) -> *const u8 {
+  let s = StrID::try_from_usize(id).and_then(StrID::try_as_str);
+  if !out_len.is_null() {
+    // Safety: the caller promises that `out_len` is valid for writing.
+    unsafe { out_len.write(s.map_or(0, str::len)) };
+  }
+  s.map_or(core::ptr::null(), str::as_ptr)
+}
+
+/// Gets the number of strings that have been interned.
+///
+/// This is the same as [interned_count](crate::interned_count).
+#[unsafe(no_mangle)]
+pub extern "C" fn str_id_count() -> usize {
+  crate::interned_count()
+}
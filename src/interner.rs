@@ -0,0 +1,413 @@
+//! A local interner, for when the global cache isn't suitable.
+
+use crate::StrID;
+use bimap::BiHashMap;
+use core::{hash::BuildHasher, num::NonZeroUsize};
+use std::{
+  collections::{BTreeMap, HashMap},
+  hash::RandomState,
+};
+
+/// An ID value from an [Interner].
+///
+/// This is just like a [StrID](crate::StrID), except that it's only
+/// meaningful to the `Interner` that created it. Passing it to some other
+/// `Interner` will resolve to some unrelated string, or to nothing at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct LocalID(NonZeroUsize);
+impl LocalID {
+  /// Unwraps the value into a raw `usize`.
+  #[inline]
+  #[must_use]
+  pub const fn as_usize(self) -> usize {
+    self.0.get()
+  }
+}
+
+/// Interns str slices into a cache that it owns, giving you back an ID value.
+///
+/// Unlike the global cache, all of the string data is freed when the
+/// `Interner` is dropped, so this is suitable for long running programs that
+/// only need a set of strings for a limited time.
+///
+/// The [LocalID] values it gives out are plain `Copy` values that don't borrow
+/// from the `Interner`, so they can be kept around past the `Interner`'s
+/// lifetime. However, they can only be turned back into strings by calling
+/// [resolve](Self::resolve) on the same `Interner`, and the `&str` that gives
+/// you borrows from the `Interner`.
+///
+/// The hasher can be picked separately for each `Interner`, with
+/// [with_hasher](Self::with_hasher). By default it's the standard library's
+/// `RandomState`, regardless of the crate features that pick the global
+/// cache's hasher.
+#[derive(Debug)]
+pub struct Interner<S = RandomState> {
+  map: BiHashMap<LocalID, Box<str>, S, S>,
+  next_id: usize,
+}
+impl Interner {
+  /// Makes a new, empty interner.
+  #[inline]
+  #[must_use]
+  pub fn new() -> Self {
+    Self::with_hasher(RandomState::new())
+  }
+}
+impl<S: BuildHasher + Clone> Interner<S> {
+  /// Makes a new, empty interner that uses the given hasher.
+  ///
+  /// ```
+  /// # use str_id::Interner;
+  /// # use std::hash::BuildHasherDefault;
+  /// # use std::collections::hash_map::DefaultHasher;
+  /// let mut interner =
+  ///   Interner::with_hasher(BuildHasherDefault::<DefaultHasher>::default());
+  /// let id = interner.intern("foo");
+  /// assert_eq!(interner.resolve(id), Some("foo"));
+  /// ```
+  #[inline]
+  #[must_use]
+  pub fn with_hasher(hasher: S) -> Self {
+    Self { map: BiHashMap::with_hashers(hasher.clone(), hasher), next_id: 1 }
+  }
+}
+impl<S: BuildHasher> Interner<S> {
+  /// Gets the ID of a str slice, inserting it into this interner if
+  /// necessary.
+  ///
+  /// ## Panics
+  /// * If the interner has used all of the available ID values.
+  #[inline]
+  #[track_caller]
+  pub fn intern(&mut self, s: &str) -> LocalID {
+    if let Some(id) = self.map.get_by_right(s) {
+      *id
+    } else {
+      let id = NonZeroUsize::new(self.next_id)
+        .map(LocalID)
+        .expect("exhausted the available LocalID values!");
+      self.next_id = self.next_id.wrapping_add(1);
+      self.map.insert(id, s.into());
+      id
+    }
+  }
+
+  /// Gets the ID of a str slice, if it's already in this interner.
+  #[inline]
+  #[must_use]
+  pub fn get(&self, s: &str) -> Option<LocalID> {
+    self.map.get_by_right(s).copied()
+  }
+
+  /// Gets the str slice associated with an ID, if the ID is in this
+  /// interner.
+  #[inline]
+  #[must_use]
+  pub fn resolve(&self, id: LocalID) -> Option<&str> {
+    self.map.get_by_left(&id).map(|s| &**s)
+  }
+
+  /// Gets the number of strings in this interner.
+  #[inline]
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self.map.len()
+  }
+
+  /// Checks if this interner has no strings in it.
+  #[inline]
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.map.is_empty()
+  }
+
+  /// Keeps only the strings that `f` gives `true` for, freeing the rest.
+  ///
+  /// The IDs of the strings that are kept don't change. **The IDs of the
+  /// strings that are removed stop being valid**: resolving one gives `None`,
+  /// and since ID values are never reused, it won't ever resolve to some other
+  /// string. Interning a removed string again gives it a new ID.
+  ///
+  /// ```
+  /// # use str_id::Interner;
+  /// let mut interner = Interner::new();
+  /// let keep = interner.intern("keep");
+  /// let drop = interner.intern("drop");
+  /// interner.retain(|_, s| s.starts_with('k'));
+  /// assert_eq!(interner.resolve(keep), Some("keep"));
+  /// assert_eq!(interner.resolve(drop), None);
+  /// ```
+  #[inline]
+  pub fn retain(&mut self, mut f: impl FnMut(LocalID, &str) -> bool) {
+    self.map.retain(|id, s| f(*id, s));
+  }
+}
+
+/// Interns every string of a local [Interner] into the global cache, giving
+/// back which [StrID] each [LocalID] became.
+///
+/// This is for building up a set of strings in an `Interner`, and then
+/// keeping some (or all) of them for good. The global cache is only write
+/// locked once for the whole batch. Equal strings always get equal IDs, so
+/// strings that were already in the global cache get the IDs that they
+/// already had.
+///
+/// Besides interning the strings themselves, this allocates a `HashMap` with
+/// an entry per string in the interner, as well as a temporary `Vec` of the
+/// strings.
+///
+/// ```
+/// # use str_id::{merge_into_global, Interner, StrID};
+/// let mut local = Interner::new();
+/// let foo = local.intern("foo");
+/// let remap = merge_into_global(&local);
+/// assert_eq!(remap[&foo], StrID::from("foo"));
+/// ```
+#[inline]
+#[must_use]
+#[track_caller]
+pub fn merge_into_global<S: BuildHasher>(
+  local: &Interner<S>,
+) -> HashMap<LocalID, StrID> {
+  let (local_ids, strs): (Vec<LocalID>, Vec<&str>) =
+    local.map.iter().map(|(id, s)| (*id, &**s)).unzip();
+  local_ids.into_iter().zip(crate::intern_all(&strs)).collect()
+}
+
+impl<S: BuildHasher + Default> Default for Interner<S> {
+  #[inline]
+  fn default() -> Self {
+    Self { map: BiHashMap::default(), next_id: 1 }
+  }
+}
+
+/// An interner that holds at most a fixed number of strings, for programs
+/// that intern an unbounded stream of strings but only need the recent ones.
+///
+/// When the interner is full and a new string is interned, the least recently
+/// used string is evicted, and its storage is freed. A string counts as used
+/// when it's interned (whether it was new or not), and when its ID is passed
+/// to [resolve](Self::resolve). Looking at the interner with
+/// [get](Self::get) or [peek](Self::peek) doesn't count as a use.
+///
+/// **A [LocalID] from a `BoundedInterner` stops being valid when its string
+/// is evicted.** Resolving it after that gives `None`. ID values are never
+/// reused, so an old ID never resolves to some other string, and interning
+/// the evicted string again gives a new ID.
+///
+/// ```
+/// # use str_id::BoundedInterner;
+/// # use core::num::NonZeroUsize;
+/// let mut interner = BoundedInterner::new(NonZeroUsize::new(2).unwrap());
+/// let a = interner.intern("a");
+/// let b = interner.intern("b");
+/// interner.intern("a"); // now "b" is the least recently used
+/// let c = interner.intern("c");
+/// assert_eq!(interner.resolve(a), Some("a"));
+/// assert_eq!(interner.resolve(b), None);
+/// assert_eq!(interner.resolve(c), Some("c"));
+/// assert_ne!(interner.intern("b"), b);
+/// ```
+#[derive(Debug)]
+pub struct BoundedInterner {
+  map: BiHashMap<LocalID, Box<str>>,
+  last_used: HashMap<LocalID, u64>,
+  by_last_use: BTreeMap<u64, LocalID>,
+  max_len: NonZeroUsize,
+  next_id: usize,
+  clock: u64,
+}
+impl BoundedInterner {
+  /// Makes a new, empty interner that holds at most `max_len` strings.
+  #[inline]
+  #[must_use]
+  pub fn new(max_len: NonZeroUsize) -> Self {
+    Self {
+      map: BiHashMap::new(),
+      last_used: HashMap::new(),
+      by_last_use: BTreeMap::new(),
+      max_len,
+      next_id: 1,
+      clock: 0,
+    }
+  }
+
+  /// Gets the ID of a str slice, inserting it into this interner if
+  /// necessary.
+  ///
+  /// If the string is new and the interner is full, the least recently used
+  /// string is evicted first.
+  ///
+  /// ## Panics
+  /// * If the interner has used all of the available ID values.
+  #[inline]
+  #[track_caller]
+  pub fn intern(&mut self, s: &str) -> LocalID {
+    if let Some(id) = self.map.get_by_right(s).copied() {
+      self.touch(id);
+      return id;
+    }
+    let id = NonZeroUsize::new(self.next_id)
+      .map(LocalID)
+      .expect("exhausted the available LocalID values!");
+    self.next_id = self.next_id.wrapping_add(1);
+    if self.map.len() >= self.max_len.get() {
+      if let Some((_, oldest)) = self.by_last_use.pop_first() {
+        self.last_used.remove(&oldest);
+        self.map.remove_by_left(&oldest);
+      }
+    }
+    self.map.insert(id, s.into());
+    self.touch(id);
+    id
+  }
+
+  /// Gets the ID of a str slice, if it's in this interner.
+  ///
+  /// This doesn't count as a use of the string.
+  #[inline]
+  #[must_use]
+  pub fn get(&self, s: &str) -> Option<LocalID> {
+    self.map.get_by_right(s).copied()
+  }
+
+  /// Gets the str slice associated with an ID, if the ID is in this
+  /// interner, counting it as a use of the string.
+  ///
+  /// This gives `None` if the string was evicted.
+  #[inline]
+  pub fn resolve(&mut self, id: LocalID) -> Option<&str> {
+    if self.map.contains_left(&id) {
+      self.touch(id);
+    }
+    self.peek(id)
+  }
+
+  /// Gets the str slice associated with an ID, if the ID is in this
+  /// interner, without counting it as a use of the string.
+  #[inline]
+  #[must_use]
+  pub fn peek(&self, id: LocalID) -> Option<&str> {
+    self.map.get_by_left(&id).map(|s| &**s)
+  }
+
+  /// Gets the number of strings in this interner.
+  #[inline]
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self.map.len()
+  }
+
+  /// Checks if this interner has no strings in it.
+  #[inline]
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.map.is_empty()
+  }
+
+  /// Gets the most strings that this interner will hold at once.
+  #[inline]
+  #[must_use]
+  pub fn max_len(&self) -> NonZeroUsize {
+    self.max_len
+  }
+
+  /// Marks an ID as the most recently used.
+  #[inline]
+  fn touch(&mut self, id: LocalID) {
+    let now = self.clock;
+    self.clock += 1;
+    if let Some(before) = self.last_used.insert(id, now) {
+      self.by_last_use.remove(&before);
+    }
+    self.by_last_use.insert(now, id);
+  }
+}
+
+/// Like an [Interner], but the strings are copied into a `bumpalo::Bump`
+/// instead of being owned by the interner itself.
+///
+/// This is only available with the `bumpalo` feature.
+///
+/// The interner borrows the arena, so the arena has to outlive the interner,
+/// and the strings that you get back from [resolve](Self::resolve) borrow from
+/// the arena rather than from the interner. That means they stay usable after
+/// the interner is dropped, for as long as the arena is alive. The string data
+/// is only freed when the arena is reset or dropped, which frees every string
+/// at once.
+///
+/// Just like with an `Interner`, the [LocalID] values can outlive both the
+/// interner and the arena, but they're only meaningful to the `ArenaInterner`
+/// that created them.
+#[cfg(feature = "bumpalo")]
+#[derive(Debug)]
+pub struct ArenaInterner<'bump> {
+  arena: &'bump bumpalo::Bump,
+  map: BiHashMap<LocalID, &'bump str>,
+  next_id: usize,
+}
+#[cfg(feature = "bumpalo")]
+impl<'bump> ArenaInterner<'bump> {
+  /// Makes a new, empty interner that allocates into the given arena.
+  #[inline]
+  #[must_use]
+  pub fn new(arena: &'bump bumpalo::Bump) -> Self {
+    Self { arena, map: BiHashMap::new(), next_id: 1 }
+  }
+
+  /// Gets the ID of a str slice, copying it into the arena if necessary.
+  ///
+  /// ## Panics
+  /// * If the interner has used all of the available ID values.
+  #[inline]
+  #[track_caller]
+  pub fn intern(&mut self, s: &str) -> LocalID {
+    if let Some(id) = self.map.get_by_right(s) {
+      *id
+    } else {
+      let id = NonZeroUsize::new(self.next_id)
+        .map(LocalID)
+        .expect("exhausted the available LocalID values!");
+      self.next_id = self.next_id.wrapping_add(1);
+      self.map.insert(id, self.arena.alloc_str(s));
+      id
+    }
+  }
+
+  /// Gets the ID of a str slice, if it's already in this interner.
+  #[inline]
+  #[must_use]
+  pub fn get(&self, s: &str) -> Option<LocalID> {
+    self.map.get_by_right(s).copied()
+  }
+
+  /// Gets the str slice associated with an ID, if the ID is in this
+  /// interner.
+  #[inline]
+  #[must_use]
+  pub fn resolve(&self, id: LocalID) -> Option<&'bump str> {
+    self.map.get_by_left(&id).copied()
+  }
+
+  /// Gets the number of strings in this interner.
+  #[inline]
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self.map.len()
+  }
+
+  /// Checks if this interner has no strings in it.
+  #[inline]
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.map.is_empty()
+  }
+
+  /// Gets the arena that this interner allocates into.
+  #[inline]
+  #[must_use]
+  pub fn arena(&self) -> &'bump bumpalo::Bump {
+    self.arena
+  }
+}
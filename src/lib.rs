@@ -1,5 +1,10 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
-#![forbid(unsafe_code)]
+#![cfg_attr(
+  not(any(feature = "ffi", feature = "bytemuck")),
+  forbid(unsafe_code)
+)]
+#![cfg_attr(any(feature = "ffi", feature = "bytemuck"), deny(unsafe_code))]
 
 //! Interns str slices, giving you back an ID value.
 //!
@@ -7,149 +12,2700 @@
 //! benefits as using `Box<Str>`, but when the same str is used in multiple
 //! locations they'll resolve to the same ID value.
 //!
+//! With the `small-id` feature the ID value is a newtyped `NonZeroU32`
+//! instead, which halves the size of an ID on 64-bit targets, at the cost of
+//! only allowing about 4 billion strings to be interned.
+//!
 //! All str slice data resides in a global cache. There is no way to purge the
 //! cache once a str slice has been interned. This library is not intended for
 //! long running programs.
+//!
+//! If you do need to free the strings at some point, an [Interner] keeps its
+//! own cache, which is freed when the `Interner` is dropped. With the
+//! `bumpalo` feature, an `ArenaInterner` does the same with the strings
+//! stored in a `bumpalo::Bump` arena. A [ScopedInterner] also frees its strings
+//! when it's dropped, and its IDs borrow it, so they can't be used after that.
+//! A [BoundedInterner] holds at most a fixed number of strings, freeing the
+//! least recently used one to make room for a new one.
+//!
+//! ## `no_std`
+//!
+//! The `std` feature is on by default. Without it, the `alloc` feature must be
+//! enabled instead, which uses `hashbrown` for the cache and `spin` for the
+//! lock, so that the crate works anywhere that has an allocator. The local
+//! [Interner] and several of the other features need `std`.
+//!
+//! ## Locking
+//!
+//! The cache is behind a [std::sync::RwLock] by default. The `parking_lot`
+//! feature uses a `parking_lot::RwLock` instead, which is generally a little
+//! faster, particularly under contention, at the cost of an extra dependency.
+//!
+//! If a thread panics while it holds the std lock for writing, the lock gets
+//! poisoned. The cache ignores the poison and keeps working, because an
+//! interrupted insert can't make the cache unsafe to use. At worst, an ID
+//! value was allocated but the entry never inserted, which just skips that ID
+//! value, or (if the panic was inside the hash map itself) only one direction
+//! of the entry was inserted. That last case breaks the guarantee that equal
+//! strings get equal IDs, since a later intern of the string won't find the
+//! entry and will give it a second ID. See [is_poisoned] and [clear_poison]
+//! for noticing and acknowledging this.
+//!
+//! With the `arc-swap` feature, looking up the string of an ID (as with
+//! [as_str](StrID::as_str)) doesn't take the lock at all. Instead, there's a
+//! second table, from IDs to strings, that's read with a single atomic load
+//! and some indexing, and is only locked when it needs to grow. Looking up the
+//! ID of a string still uses the lock. The `read_path` bench times this: on a
+//! single core machine it measured about 18ns per lookup with `arc-swap`
+//! against about 20ns with the std lock, from 1 thread or from 8. Readers of
+//! the table also don't all update the lock's shared reader count, which
+//! should matter more with many cores, but that isn't what was measured. The
+//! cost is that each string also takes up a slot of about 32 bytes in the
+//! second table, and the table grows in power of two steps. If the ID counter
+//! jumps far ahead (such as from importing a snapshot with very high IDs), the
+//! second table stops growing, and every lookup of a newer ID takes the lock
+//! again.
+//!
+//! ## Thread-local cache
+//!
+//! With the `thread-local-cache` feature, each thread also keeps a small cache
+//! of recently interned strings, which is checked before the global cache.
+//! When the same few strings are interned over and over, this skips taking the
+//! lock at all. The cost is 1.5KiB of memory (on 64-bit targets) per thread
+//! that interns strings.
+//!
+//! ## Hashing
+//!
+//! By default the cache hashes with `fnv`. The `ahash` feature switches it to
+//! `ahash` instead, and takes precedence over `fnv` if both are enabled. With
+//! neither feature the standard library's default hasher is used. Any hasher
+//! that's compiled in can also be picked when the program starts, with an
+//! [InternConfig].
+//!
+//! ## Tracing
+//!
+//! The `tracing` feature emits `tracing` events as new strings are interned.
+//! See `set_tracing_interval` for the details.
+//!
+//! ## Normalization
+//!
+//! Two strings that look the same can still be made of different bytes, such
+//! as an accented letter written as one precomposed character or as a letter
+//! followed by a combining accent. Normally these are different strings, with
+//! different IDs.
+//!
+//! With the `normalize` feature, every string is put into Unicode NFC form
+//! before it's looked up in or inserted into the cache, so canonically
+//! equivalent strings share one ID. This changes which strings are considered
+//! equal, and [as_str](StrID::as_str) gives back the NFC form, which might not
+//! be the exact bytes that were interned. The `unicode-normalization` feature
+//! only provides [StrID::from_normalized], for opting in on a per-call basis.
+
+extern crate alloc;
+#[cfg(all(test, not(feature = "std")))]
+extern crate std;
+
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("str_id needs either the `std` feature or the `alloc` feature");
+
+use alloc::{
+  borrow::Cow, boxed::Box, rc::Rc, string::String, sync::Arc, vec::Vec,
+};
+#[cfg(feature = "std")]
+use bimap::BiHashMap;
+use core::{num::NonZeroUsize, sync::atomic::Ordering};
+use hasher::{CacheBuildHasher, HasherKind};
+
+#[cfg(feature = "std")]
+mod interner;
+#[cfg(feature = "std")]
+pub use interner::*;
+
+#[cfg(feature = "std")]
+mod scoped;
+#[cfg(feature = "std")]
+pub use scoped::*;
+
+#[cfg(not(feature = "std"))]
+mod no_std_bimap;
+
+mod hasher;
+
+#[cfg(feature = "thread-local-cache")]
+mod local_cache;
+
+#[cfg(feature = "arc-swap")]
+mod reverse_table;
+
+mod snapshot;
+pub use snapshot::*;
+
+mod namespace;
+pub use namespace::*;
+
+mod config;
+pub use config::*;
+
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+mod arbitrary_support;
+
+#[cfg(feature = "rkyv")]
+mod rkyv_support;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "bytemuck")]
+mod bytemuck_support;
+
+#[cfg(feature = "serde")]
+mod serde_support;
+#[cfg(feature = "serde")]
+pub use serde_support::StrIDRaw;
+
+/// Interns a string literal, giving its [StrID].
+///
+/// This uses [StrID::from_static], so the literal doesn't get copied.
+///
+/// ```
+/// # use str_id::{str_id, StrID};
+/// assert_eq!(str_id!("hello"), StrID::from("hello"));
+/// ```
+#[macro_export]
+macro_rules! str_id {
+  ($s:literal) => {
+    $crate::StrID::from_static($s)
+  };
+}
+
+/// An easier name to type because you don't have to use non-letter characters.
+pub type StaticStr = &'static str;
+
+#[cfg(feature = "std")]
+type BiMap = BiHashMap<StrID, StaticStr, CacheBuildHasher, CacheBuildHasher>;
+#[cfg(not(feature = "std"))]
+type BiMap = no_std_bimap::BiMap;
+
+/// Makes an empty map that hashes with the given hasher.
+#[inline]
+fn new_map(hasher: HasherKind) -> BiMap {
+  let build = CacheBuildHasher::new(hasher);
+  BiMap::with_hashers(build.clone(), build)
+}
+
+#[cfg(not(feature = "small-id"))]
+type IdInt = usize;
+#[cfg(not(feature = "small-id"))]
+type IdValue = NonZeroUsize;
+#[cfg(not(feature = "small-id"))]
+type AtomicIdValue = core::sync::atomic::AtomicUsize;
+#[cfg(feature = "small-id")]
+type IdInt = u32;
+#[cfg(feature = "small-id")]
+type IdValue = core::num::NonZeroU32;
+#[cfg(feature = "small-id")]
+type AtomicIdValue = core::sync::atomic::AtomicU32;
+
+static NEXT_STR_ID: AtomicIdValue = AtomicIdValue::new(1);
+
+/// The ID of the empty string, or 0 if [StrID::empty] hasn't been called yet.
+static EMPTY_ID: AtomicIdValue = AtomicIdValue::new(0);
+
+#[cfg(all(feature = "std", not(feature = "parking_lot")))]
+type CacheLock = std::sync::RwLock<BiMap>;
+#[cfg(all(feature = "std", not(feature = "parking_lot")))]
+type ReadGuard = std::sync::RwLockReadGuard<'static, BiMap>;
+#[cfg(all(feature = "std", not(feature = "parking_lot")))]
+type WriteGuard = std::sync::RwLockWriteGuard<'static, BiMap>;
+#[cfg(feature = "parking_lot")]
+type CacheLock = parking_lot::RwLock<BiMap>;
+#[cfg(feature = "parking_lot")]
+type ReadGuard = parking_lot::RwLockReadGuard<'static, BiMap>;
+#[cfg(feature = "parking_lot")]
+type WriteGuard = parking_lot::RwLockWriteGuard<'static, BiMap>;
+#[cfg(not(feature = "std"))]
+type CacheLock = spin::RwLock<BiMap>;
+#[cfg(not(feature = "std"))]
+type ReadGuard = spin::RwLockReadGuard<'static, BiMap>;
+#[cfg(not(feature = "std"))]
+type WriteGuard = spin::RwLockWriteGuard<'static, BiMap>;
+
+#[cfg(feature = "std")]
+type OnceCell<T> = std::sync::OnceLock<T>;
+#[cfg(not(feature = "std"))]
+type OnceCell<T> = spin::Once<T>;
+
+/// The number of shards that the cache is split into, unless an
+/// [InternConfig] picks some other number.
+///
+/// Each shard has its own lock, so strings that land in different shards can
+/// be interned at the same time without blocking each other. This is 1 by
+/// default, and 16 with the `sharded` feature. See [shard_count] for the
+/// number of shards that the cache actually has.
+///
+/// A string's shard is picked by hashing the string. Each shard only hands out
+/// IDs that are equal to the shard's index modulo the shard count, so the
+/// shard of an ID can be found from the ID value alone. That means that with
+/// more than one shard, the ID values aren't consecutive.
+pub const DEFAULT_SHARD_COUNT: usize =
+  if cfg!(feature = "sharded") { 16 } else { 1 };
+
+/// The old name of [DEFAULT_SHARD_COUNT].
+///
+/// Since an [InternConfig] can pick the number of shards, this isn't always
+/// the number that the cache has. Use [shard_count] for that.
+#[deprecated(note = "use `shard_count()`, or `DEFAULT_SHARD_COUNT`")]
+pub const SHARD_COUNT: usize = DEFAULT_SHARD_COUNT;
+
+/// The most shards that an [InternConfig] can split the cache into.
+pub const MAX_SHARD_COUNT: usize = 64;
+
+/// The global cache, which is set up the first time that it's used.
+struct Cache {
+  shards: Box<[CacheLock]>,
+  /// Kept so that [reset_cache] can make new maps with the same hasher.
+  #[cfg(feature = "testing")]
+  hasher: HasherKind,
+}
+
+static STR_CACHE: OnceCell<Cache> = OnceCell::new();
+
+/// Sets up the cache, unless it's already set up.
+///
+/// This gives `true` if the cache was set up with these settings, or `false`
+/// if it had already been set up.
+#[inline]
+fn init_cache(shards: usize, capacity: usize, hasher: HasherKind) -> bool {
+  let per_shard = capacity.div_ceil(shards);
+  let mut was_set = false;
+  let init = || {
+    was_set = true;
+    let shards = (0..shards)
+      .map(|_| {
+        let mut map = new_map(hasher);
+        map.reserve(per_shard);
+        CacheLock::new(map)
+      })
+      .collect();
+    Cache {
+      shards,
+      #[cfg(feature = "testing")]
+      hasher,
+    }
+  };
+  #[cfg(feature = "std")]
+  STR_CACHE.get_or_init(init);
+  #[cfg(not(feature = "std"))]
+  STR_CACHE.call_once(init);
+  was_set
+}
+
+/// Gets all the shards of the cache, initializing it if necessary.
+#[inline]
+fn cache() -> &'static [CacheLock] {
+  if let Some(cache) = STR_CACHE.get() {
+    return &cache.shards;
+  }
+  init_cache(DEFAULT_SHARD_COUNT, 0, HasherKind::default());
+  cache()
+}
+
+/// Gets all the shards of the cache, or none if it hasn't been set up yet.
+#[inline]
+fn cache_if_init() -> &'static [CacheLock] {
+  STR_CACHE.get().map_or(&[], |cache| &cache.shards)
+}
+
+/// Gets the number of shards that the cache is split into.
+///
+/// This is fixed once the cache is set up. Before that, it's the number of
+/// shards that the cache will have unless an [InternConfig] picks some other
+/// number, which is [DEFAULT_SHARD_COUNT]. Checking this doesn't set up the
+/// cache.
+#[inline]
+#[must_use]
+pub fn shard_count() -> usize {
+  STR_CACHE.get().map_or(DEFAULT_SHARD_COUNT, |cache| cache.shards.len())
+}
+
+/// Sets up the cache with room for at least `capacity` strings.
+///
+/// Normally the cache starts out empty the first time that it's used, and
+/// then gets rehashed over and over as it grows. If this is called before any
+/// strings are interned, the cache starts out with the given capacity
+/// instead. When the cache is sharded, the capacity is split evenly between
+/// the shards.
+///
+/// This gives `true` if the cache was set up with the capacity. If the cache
+/// had already been set up (because a string was already interned, or
+/// anything else already used the cache), nothing happens and this gives
+/// `false`. In that case, use [reserve] instead.
+#[inline]
+pub fn init_with_capacity(capacity: usize) -> bool {
+  init_cache(DEFAULT_SHARD_COUNT, capacity, HasherKind::default())
+}
+
+/// Hashes a string with FNV-1a.
+///
+/// This is used instead of the cache's own hasher wherever the result needs
+/// to be the same in every run of the program.
+#[inline]
+fn fnv1a(s: &str) -> u64 {
+  let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+  for byte in s.bytes() {
+    hash ^= u64::from(byte);
+    hash = hash.wrapping_mul(0x0100_0000_01b3);
+  }
+  hash
+}
+
+/// Gets the index of the shard that a string belongs in.
+#[inline]
+fn shard_of_str(s: &str) -> usize {
+  let shards = cache().len();
+  if shards == 1 { 0 } else { (fnv1a(s) % shards as u64) as usize }
+}
+
+/// Gets the index of the shard that an ID belongs in.
+#[inline]
+fn shard_of_id(id: StrID) -> usize {
+  id.as_usize() % cache().len()
+}
+
+/// Checks if any of the cache's locks is poisoned.
+///
+/// A lock is poisoned when a thread panicked while holding it for writing,
+/// which might have left an entry half inserted (see the crate docs on
+/// locking). The cache keeps working either way, but this lets you notice
+/// that it happened. The poison stays until [clear_poison] is called.
+///
+/// Only the std lock can be poisoned, so with the `parking_lot` feature, or
+/// without `std`, this is always `false`.
+#[inline]
+#[must_use]
+pub fn is_poisoned() -> bool {
+  #[cfg(all(feature = "std", not(feature = "parking_lot")))]
+  {
+    cache_if_init().iter().any(|rw_lock| rw_lock.is_poisoned())
+  }
+  #[cfg(any(feature = "parking_lot", not(feature = "std")))]
+  {
+    false
+  }
+}
+
+/// Clears the poison from all of the cache's locks.
+///
+/// This doesn't repair anything, it just marks the poison as seen, so that
+/// [is_poisoned] goes back to `false` until another panic happens. Without
+/// the std lock this does nothing.
+#[inline]
+pub fn clear_poison() {
+  #[cfg(all(feature = "std", not(feature = "parking_lot")))]
+  for rw_lock in cache_if_init() {
+    rw_lock.clear_poison();
+  }
+}
+
+/// Read locks the cache.
+///
+/// If the std lock is poisoned the poison is ignored. The `parking_lot` and
+/// `spin` locks don't poison at all.
+#[inline]
+fn read_lock(rw_lock: &'static CacheLock) -> ReadGuard {
+  #[cfg(all(feature = "std", not(feature = "parking_lot")))]
+  {
+    rw_lock.read().unwrap_or_else(std::sync::PoisonError::into_inner)
+  }
+  #[cfg(any(feature = "parking_lot", not(feature = "std")))]
+  {
+    rw_lock.read()
+  }
+}
+
+/// Read locks every shard of the cache, in order.
+#[inline]
+fn read_all() -> Vec<ReadGuard> {
+  cache().iter().map(read_lock).collect()
+}
+
+/// Write locks every shard of the cache, in order.
+///
+/// Anything that needs more than one shard at once has to lock them in index
+/// order, which is what keeps this from deadlocking.
+#[inline]
+fn write_all() -> Vec<WriteGuard> {
+  cache().iter().map(write_lock).collect()
+}
+
+/// Write locks the cache, with the same poison handling as [read_lock].
+#[inline]
+fn write_lock(rw_lock: &'static CacheLock) -> WriteGuard {
+  #[cfg(all(feature = "std", not(feature = "parking_lot")))]
+  {
+    rw_lock.write().unwrap_or_else(std::sync::PoisonError::into_inner)
+  }
+  #[cfg(any(feature = "parking_lot", not(feature = "std")))]
+  {
+    rw_lock.write()
+  }
+}
+
+static INTERN_OBSERVER: OnceCell<fn(StrID, StaticStr)> = OnceCell::new();
+
+/// Sets a function to be called every time a new string is inserted into the
+/// cache.
+///
+/// This is meant for things like auditing, or tracking how much the cache
+/// grows. The observer gets the new ID and its string. It's called at most
+/// once per distinct string, by whichever thread inserted the string, after
+/// the lock has been released. That means the observer can safely look
+/// things up in the cache, but if it interns a *new* string, that will call
+/// the observer again, so make sure that it can't recurse forever. Entries
+/// inserted by [import_snapshot] aren't observed.
+///
+/// Only one observer can ever be set. This gives `true` if `f` was set as the
+/// observer, or `false` if there already was an observer (which is kept).
+///
+/// When no observer is set, the cost is a single atomic load per new string.
+#[inline]
+pub fn set_intern_observer(f: fn(StrID, StaticStr)) -> bool {
+  let mut was_set = false;
+  let init = || {
+    was_set = true;
+    f
+  };
+  #[cfg(feature = "std")]
+  INTERN_OBSERVER.get_or_init(init);
+  #[cfg(not(feature = "std"))]
+  INTERN_OBSERVER.call_once(init);
+  was_set
+}
+
+/// If [as_str](StrID::as_str) panics on an ID that isn't in the cache.
+static PANIC_ON_MISSING_ID: core::sync::atomic::AtomicBool =
+  core::sync::atomic::AtomicBool::new(false);
+
+/// What [as_str](StrID::as_str) does with an ID that isn't in the cache.
+///
+/// An ID obtained from this crate is always in the cache, so this only
+/// matters for IDs that were forged (such as with
+/// [from_usize_unchecked](StrID::from_usize_unchecked)) or carried over from
+/// some other process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MissingIdPolicy {
+  /// Give back an empty string. This is the default.
+  #[default]
+  Empty,
+  /// Panic, so that a bad ID is caught right where it's used.
+  Panic,
+}
+
+/// Sets what [as_str](StrID::as_str) does with an ID that isn't in the cache.
+///
+/// This is meant to be set once when the program starts, but it can be
+/// changed at any time, and it applies to every thread. The policy applies to
+/// everything that's built on `as_str`, such as [len](StrID::len) and the
+/// `Deref` to `str`. The `Debug` format of an ID never panics, and
+/// [try_as_str](StrID::try_as_str) always gives `None` (ignoring the policy).
+///
+/// ```should_panic
+/// # use str_id::{set_missing_id_policy, MissingIdPolicy, StrID};
+/// set_missing_id_policy(MissingIdPolicy::Panic);
+/// let forged = StrID::from_usize_unchecked(StrID::MAX.as_nonzero());
+/// forged.as_str();
+/// ```
+#[inline]
+pub fn set_missing_id_policy(policy: MissingIdPolicy) {
+  let panics = policy == MissingIdPolicy::Panic;
+  PANIC_ON_MISSING_ID.store(panics, Ordering::Relaxed);
+}
+
+/// Gets the current [MissingIdPolicy].
+#[inline]
+#[must_use]
+pub fn missing_id_policy() -> MissingIdPolicy {
+  if PANIC_ON_MISSING_ID.load(Ordering::Relaxed) {
+    MissingIdPolicy::Panic
+  } else {
+    MissingIdPolicy::Empty
+  }
+}
+
+/// The function that [force_write_lock_yield] set, if any.
+#[cfg(feature = "test-seams")]
+static WRITE_LOCK_GAP_HOOK: std::sync::RwLock<Option<fn()>> =
+  std::sync::RwLock::new(None);
+
+/// Sets a function to be called by every thread that's interning a new
+/// string, right after it drops the read lock and right before it takes the
+/// write lock.
+///
+/// This is a seam for testing the crate itself, and it's only available with
+/// the `test-seams` feature. The window between the two locks is where
+/// another thread can insert the same string first, which interning has to
+/// check for again once it has the write lock. That race is very hard to
+/// provoke on its own, but with this a test can pause one thread in the
+/// window while another thread interns the string. Passing `None` removes the
+/// hook.
+///
+/// ```
+/// # use str_id::{force_write_lock_yield, StrID};
+/// use std::sync::Barrier;
+/// static STEP: Barrier = Barrier::new(2);
+///
+/// force_write_lock_yield(Some(|| {
+///   STEP.wait(); // tell the main thread that we're between the locks
+///   STEP.wait(); // wait for it to intern the string
+/// }));
+/// let paused = std::thread::spawn(|| StrID::intern_reporting("race"));
+/// STEP.wait();
+/// force_write_lock_yield(None);
+/// let (id, inserted) = StrID::intern_reporting("race");
+/// assert!(inserted);
+/// STEP.wait();
+/// // the paused thread found the string when it checked again.
+/// assert_eq!(paused.join().unwrap(), (id, false));
+/// ```
+#[cfg(feature = "test-seams")]
+#[doc(hidden)]
+#[inline]
+pub fn force_write_lock_yield(hook: Option<fn()>) {
+  let mut slot = WRITE_LOCK_GAP_HOOK
+    .write()
+    .unwrap_or_else(std::sync::PoisonError::into_inner);
+  *slot = hook;
+}
+
+/// Calls the [force_write_lock_yield] hook, if there is one.
+#[cfg(feature = "test-seams")]
+#[inline]
+fn write_lock_gap() {
+  let hook = *WRITE_LOCK_GAP_HOOK
+    .read()
+    .unwrap_or_else(std::sync::PoisonError::into_inner);
+  if let Some(hook) = hook {
+    hook();
+  }
+}
+
+/// How many strings apart the `tracing` size events are.
+#[cfg(feature = "tracing")]
+static TRACING_INTERVAL: core::sync::atomic::AtomicUsize =
+  core::sync::atomic::AtomicUsize::new(1024);
+
+/// The number of entries in the cache, as counted by the `tracing` events.
+///
+/// Each insert takes its own count from this, so every count is reported
+/// exactly once, even when several threads insert at the same time.
+#[cfg(feature = "tracing")]
+static TRACED_COUNT: core::sync::atomic::AtomicUsize =
+  core::sync::atomic::AtomicUsize::new(0);
+
+/// Sets how often a `debug` event about the size of the cache is emitted.
+///
+/// With the `tracing` feature, every newly interned string emits a `trace`
+/// event with the string, its ID, its length, and the number of strings in the
+/// cache once it was inserted. Each time the number of strings crosses a
+/// multiple of `interval`, a `debug` event is also emitted, once per multiple,
+/// even when a batch (such as from [intern_all]) crosses several multiples at
+/// once. Strings inserted by [import_snapshot] aren't traced one by one, but
+/// they do count towards the size events. The default interval is 1024, and
+/// an interval of 0 turns the `debug` events off.
+///
+/// The events are emitted after the cache's lock has been released, so a
+/// subscriber can use the cache without deadlocking.
+#[cfg(feature = "tracing")]
+#[inline]
+pub fn set_tracing_interval(interval: usize) {
+  TRACING_INTERVAL.store(interval, Ordering::Relaxed);
+}
+
+/// Emits the `tracing` events for the strings that were just inserted into the
+/// cache, all at once.
+///
+/// The strings take a run of counts in one step, so a batch always sees every
+/// count that it takes the cache through.
+#[cfg(feature = "tracing")]
+#[inline]
+fn trace_interned(ids: &[StrID]) {
+  let old = TRACED_COUNT.fetch_add(ids.len(), Ordering::Relaxed);
+  // when `debug` events are off so are `trace` events.
+  if !tracing::enabled!(tracing::Level::DEBUG) {
+    return;
+  }
+  let interval = TRACING_INTERVAL.load(Ordering::Relaxed);
+  for (count, id) in (old + 1..).zip(ids) {
+    let s = id.as_str();
+    tracing::trace!(id = id.as_usize(), len = s.len(), count, s, "interned");
+    if interval != 0 && count % interval == 0 {
+      tracing::debug!(count, "the cache reached a size threshold");
+    }
+  }
+}
+
+/// Counts `n` strings that were inserted into the cache without being traced
+/// one by one, emitting the `debug` event for each size threshold crossed.
+#[cfg(feature = "tracing")]
+#[inline]
+fn trace_grown(n: usize) {
+  let old = TRACED_COUNT.fetch_add(n, Ordering::Relaxed);
+  let interval = TRACING_INTERVAL.load(Ordering::Relaxed);
+  if interval == 0 || !tracing::enabled!(tracing::Level::DEBUG) {
+    return;
+  }
+  let first = (old / interval + 1).saturating_mul(interval);
+  for count in (first..=old + n).step_by(interval) {
+    tracing::debug!(count, "the cache reached a size threshold");
+  }
+}
+
+/// Runs the intern observer (if any) for newly interned IDs, and emits the
+/// `tracing` events for them.
+///
+/// This must only be called while *not* holding any of the cache's locks.
+#[inline]
+fn notify_interned(ids: &[StrID]) {
+  #[cfg(feature = "tracing")]
+  trace_interned(ids);
+  if let Some(observer) = INTERN_OBSERVER.get() {
+    for id in ids {
+      observer(*id, id.as_str());
+    }
+  }
+}
+
+#[cfg(target_has_atomic = "64")]
+type AtomicCounter = core::sync::atomic::AtomicU64;
+#[cfg(not(target_has_atomic = "64"))]
+type AtomicCounter = core::sync::atomic::AtomicUsize;
+
+static HITS: AtomicCounter = AtomicCounter::new(0);
+static MISSES: AtomicCounter = AtomicCounter::new(0);
+
+/// Counts an intern as either a hit or a miss.
+#[inline]
+fn count_intern(inserted: bool) {
+  let counter = if inserted { &MISSES } else { &HITS };
+  counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Statistics about how interning has gone so far, from [intern_stats].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct InternStats {
+  /// How many times a string was interned that was already in the cache.
+  pub hits: u64,
+  /// How many times a string was interned that had to be inserted into the
+  /// cache.
+  pub misses: u64,
+  /// How many strings are in the cache.
+  pub entries: usize,
+}
+
+/// Gets the cache hit and miss counts, and the number of entries.
+///
+/// Every intern counts as either a hit, when the string was already in the
+/// cache (including the thread-local cache), or a miss, when it had to be
+/// inserted. Lookups that never insert, like [StrID::get], aren't counted.
+/// This is handy for deciding if features like `thread-local-cache`, or
+/// calling [reserve], are worth it for a particular program.
+///
+/// The counters are updated with `Relaxed` atomics and the values are read
+/// one at a time, so while other threads are interning strings they might not
+/// exactly agree with each other.
+#[inline]
+#[must_use]
+#[allow(clippy::unnecessary_cast)]
+pub fn intern_stats() -> InternStats {
+  InternStats {
+    hits: HITS.load(Ordering::Relaxed) as u64,
+    misses: MISSES.load(Ordering::Relaxed) as u64,
+    entries: interned_count(),
+  }
+}
+
+/// A summary of what's in the cache, from [cache_report].
+///
+/// With the `serde` feature this can be serialized, as a struct with the same
+/// field names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CacheReport {
+  /// How many strings are in the cache.
+  pub entries: usize,
+  /// The total length in bytes of all the strings.
+  pub total_bytes: usize,
+  /// The length in bytes of the longest string.
+  pub longest_len: usize,
+  /// The length in bytes of the shortest string.
+  pub shortest_len: usize,
+  /// The average length in bytes of the strings, rounded down.
+  pub average_len: usize,
+}
+
+/// Gets a summary of what's in the cache.
+///
+/// This is all computed in one pass over the cache, with every shard read
+/// locked the whole time, so the values all agree with each other. That's
+/// `O(n)` in the number of entries. When the cache is empty, every value is
+/// 0.
+#[inline]
+#[must_use]
+pub fn cache_report() -> CacheReport {
+  let mut report =
+    CacheReport { shortest_len: usize::MAX, ..CacheReport::default() };
+  for read in read_all() {
+    for (_, s) in read.iter() {
+      report.entries += 1;
+      report.total_bytes += s.len();
+      report.longest_len = report.longest_len.max(s.len());
+      report.shortest_len = report.shortest_len.min(s.len());
+    }
+  }
+  match report.total_bytes.checked_div(report.entries) {
+    Some(average_len) => report.average_len = average_len,
+    None => report.shortest_len = 0,
+  }
+  report
+}
+
+/// Gets the ID of the longest interned string.
+///
+/// When several strings are tied for the longest, this gives the one with the
+/// lowest ID, which is the one that was interned first. This is `None` when
+/// the cache is empty. This walks the entire cache, so it's `O(n)` in the
+/// number of entries.
+///
+/// This is mostly for tracking down where a surprisingly big string in the
+/// cache came from.
+#[inline]
+#[must_use]
+pub fn longest_interned() -> Option<StrID> {
+  read_all()
+    .iter()
+    .flat_map(|read| read.iter())
+    .min_by_key(|(id, s)| (core::cmp::Reverse(s.len()), **id))
+    .map(|(id, _)| *id)
+}
+
+/// Gets the ID of the shortest interned string.
+///
+/// Just like with [longest_interned], ties go to the lowest ID, an empty cache
+/// gives `None`, and this is `O(n)` in the number of entries.
+#[inline]
+#[must_use]
+pub fn shortest_interned() -> Option<StrID> {
+  read_all()
+    .iter()
+    .flat_map(|read| read.iter())
+    .min_by_key(|(id, s)| (s.len(), **id))
+    .map(|(id, _)| *id)
+}
+
+/// Gets the number of strings that have been interned.
+///
+/// Since the cache is never purged, this only ever goes up.
+#[inline]
+#[must_use]
+pub fn interned_count() -> usize {
+  cache_if_init().iter().map(|rw_lock| read_lock(rw_lock).len()).sum()
+}
+
+/// Gets the total length in bytes of all the interned strings.
+///
+/// This walks the entire cache, so it's `O(n)` in the number of entries.
+/// Divide by [interned_count] to get the average string length.
+#[inline]
+#[must_use]
+pub fn interned_bytes() -> usize {
+  cache_if_init()
+    .iter()
+    .map(|rw_lock| {
+      read_lock(rw_lock).right_values().map(|s| s.len()).sum::<usize>()
+    })
+    .sum()
+}
+
+/// Gets a snapshot of every `(id, str)` pair in the cache.
+///
+/// The lock is only held while copying the pairs out, so it's fine to intern
+/// more strings while going over the output. The order of the pairs is
+/// unspecified, and can differ between calls.
+#[inline]
+#[must_use]
+pub fn collect_interned() -> Vec<(StrID, StaticStr)> {
+  let mut out = Vec::new();
+  for rw_lock in cache_if_init() {
+    let read = read_lock(rw_lock);
+    out.extend(read.iter().map(|(id, s)| (*id, *s)));
+  }
+  out
+}
+
+/// Iterates over every `(id, str)` pair in the cache.
+///
+/// The pairs are copied out of the cache up front, so the lock isn't held
+/// while iterating, and it's fine to intern more strings in the loop body.
+/// Strings interned after this is called aren't included. The order of the
+/// pairs is unspecified.
+///
+/// ```
+/// let foo = str_id::StrID::from("foo");
+/// assert!(str_id::iter().any(|(id, s)| id == foo && s == "foo"));
+/// ```
+#[inline]
+#[must_use]
+pub fn iter() -> CacheIter {
+  CacheIter { entries: collect_interned().into_iter() }
+}
+
+/// An iterator over the pairs in the cache, from [iter].
+#[derive(Debug, Clone)]
+pub struct CacheIter {
+  entries: alloc::vec::IntoIter<(StrID, StaticStr)>,
+}
+impl Iterator for CacheIter {
+  type Item = (StrID, StaticStr);
+
+  #[inline]
+  fn next(&mut self) -> Option<Self::Item> {
+    self.entries.next()
+  }
+
+  #[inline]
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.entries.size_hint()
+  }
+}
+impl ExactSizeIterator for CacheIter {}
+impl core::iter::FusedIterator for CacheIter {}
+
+/// Like [collect_interned], but with the pairs sorted by ascending ID.
+///
+/// IDs are handed out in order, so this is the order that the strings were
+/// first interned in. Two runs of a program that intern the same strings in
+/// the same order give the same output, which makes this good for dumps that
+/// should be reproducible. Sorting makes this `O(n log n)` in the number of
+/// entries.
+#[inline]
+#[must_use]
+pub fn iter_ordered() -> Vec<(StrID, StaticStr)> {
+  let mut out = collect_interned();
+  out.sort_unstable_by_key(|(id, _)| *id);
+  out
+}
+
+/// Gets the ID of every interned string that starts with `prefix`.
+///
+/// This walks the entire cache, so it's `O(n)` in the number of entries. An
+/// empty prefix matches every string. The order of the IDs is unspecified.
+#[inline]
+#[must_use]
+pub fn find_by_prefix(prefix: &str) -> Vec<StrID> {
+  let mut out = Vec::new();
+  for rw_lock in cache_if_init() {
+    let read = read_lock(rw_lock);
+    out.extend(
+      read.iter().filter(|(_, s)| s.starts_with(prefix)).map(|(id, _)| *id),
+    );
+  }
+  out
+}
+
+/// Gets how many more strings can be interned before the ID values run out.
+///
+/// This is only a snapshot, since other threads can be interning strings at
+/// the same time.
+#[inline]
+#[must_use]
+#[allow(clippy::unnecessary_cast)]
+pub fn remaining_ids() -> usize {
+  let shards = shard_count() as IdInt;
+  // the largest counter value that still gives a valid ID in every shard.
+  let max = (IdInt::MAX - (shards - 1)) / shards;
+  let next = NEXT_STR_ID.load(Ordering::Relaxed);
+  if next == 0 || next > max { 0 } else { (max - next) as usize + 1 }
+}
+
+/// Gets the ID value that the next newly interned string will get.
+///
+/// This only peeks at the counter, it doesn't use up the value. Comparing it
+/// before and after some code runs tells you how many new strings that code
+/// interned. It's only a snapshot though, since other threads can be
+/// interning strings at the same time, so the value might already be stale by
+/// the time you look at it.
+///
+/// When the cache is sharded this is the counter that all of the shards share,
+/// so the actual next ID is this times [shard_count], plus the index of the
+/// shard that the string lands in. If this is 0, the ID values have run out.
+#[inline]
+#[must_use]
+#[allow(clippy::unnecessary_cast)]
+pub fn next_id() -> usize {
+  NEXT_STR_ID.load(Ordering::Relaxed) as usize
+}
+
+/// Reserves capacity in the cache for at least `additional` more strings.
+///
+/// Call this before interning a large batch of strings to avoid growing the
+/// cache over and over along the way. It's fine to call this any number of
+/// times. This never shrinks the cache.
+///
+/// When the cache is sharded, the space is split evenly between the shards.
+#[inline]
+pub fn reserve(additional: usize) {
+  let shards = cache();
+  let per_shard = additional.div_ceil(shards.len());
+  for rw_lock in shards {
+    write_lock(rw_lock).reserve(per_shard);
+  }
+}
+
+/// Shrinks the cache's hash tables to fit the strings that are in it.
+///
+/// This is useful after a big batch of strings has been interned, such as
+/// after using [reserve]. Only the spare capacity of the hash tables is
+/// freed. The interned strings themselves are never freed, so this doesn't
+/// make the cache use any less memory for them.
+///
+/// Each shard is write locked in turn while it's shrunk.
+#[inline]
+pub fn shrink_to_fit() {
+  for rw_lock in cache() {
+    write_lock(rw_lock).shrink_to_fit();
+  }
+}
+
+/// Interns a str slice, giving back both its ID and the cache's `'static` copy
+/// of the string.
+///
+/// This is the same as interning the string and then calling
+/// [as_str](StrID::as_str) on the ID, but it only locks the cache once instead
+/// of twice, which helps when the string is wanted right away (such as for
+/// logging). When the string was already interned, the `&'static str` is the
+/// one that's already in the cache, otherwise it's the newly leaked one.
+///
+/// ```
+/// # use str_id::{intern_and_str, StrID};
+/// let (id, s) = intern_and_str("foo");
+/// assert_eq!(id, StrID::from("foo"));
+/// assert_eq!(s, "foo");
+/// ```
+///
+/// ## Panics
+/// * If the string has to be inserted, and all of the ID values are used up.
+#[inline]
+#[track_caller]
+pub fn intern_and_str(s: &str) -> (StrID, StaticStr) {
+  #[cfg(feature = "normalize")]
+  if let Cow::Owned(normalized) = nfc(s) {
+    return intern_and_str(&normalized);
+  }
+  #[cfg(feature = "thread-local-cache")]
+  if let Some(pair) = local_cache::get(s) {
+    count_intern(false);
+    return pair;
+  }
+  let shard = shard_of_str(s);
+  let rw_lock = &cache()[shard];
+  let read = read_lock(rw_lock);
+  let found =
+    read.get_by_right(s).and_then(|id| Some((*id, *read.get_by_left(id)?)));
+  drop(read);
+  let (pair, inserted) = match found {
+    Some(pair) => (pair, false),
+    None => {
+      let prepared = s.prepare();
+      #[cfg(feature = "test-seams")]
+      write_lock_gap();
+      let mut write = write_lock(rw_lock);
+      let result = StrID::try_intern_locked(&mut write, shard, prepared).map(
+        |(id, new)| {
+          // the string is always in the shard right after interning it.
+          ((id, write.get_by_left(&id).copied().unwrap_or("")), new)
+        },
+      );
+      drop(write);
+      let Ok(((id, s), inserted)) = result else { exhausted() };
+      if inserted {
+        notify_interned(&[id]);
+      }
+      ((id, s), inserted)
+    }
+  };
+  #[cfg(feature = "thread-local-cache")]
+  local_cache::insert(pair.0, pair.1);
+  count_intern(inserted);
+  pair
+}
+
+/// Interns a str slice only if it's at least `min_len` bytes long.
+///
+/// Every interned string is kept forever, and deduplicating very short
+/// strings usually isn't worth that. **`None` means that the string is too
+/// short to be worth interning**, and the caller should keep it inline (such
+/// as in a small string type) instead. The cache isn't looked at at all for a
+/// short string, so this gives `None` even if the string happens to be
+/// interned already.
+///
+/// ```
+/// # use str_id::maybe_intern;
+/// assert_eq!(maybe_intern("ab", 3), None);
+/// assert_eq!(maybe_intern("abc", 3).unwrap(), "abc");
+/// ```
+#[inline]
+#[must_use]
+#[track_caller]
+pub fn maybe_intern(s: &str, min_len: usize) -> Option<StrID> {
+  if s.len() < min_len { None } else { Some(StrID::from(s)) }
+}
+
+/// Interns every string in the slice, giving back their IDs in the same order.
+///
+/// This takes the write lock just once for the whole batch, instead of once
+/// per string. Equal strings get equal IDs, both within the batch and with
+/// anything already in the cache.
+///
+/// When the cache is sharded, this write locks every shard for the whole
+/// batch.
+///
+/// ```
+/// # use str_id::{intern_all, StrID};
+/// let ids = intern_all(&["x", "y", "x"]);
+/// assert_eq!(ids[0], ids[2]);
+/// // the items can be IDs themselves, since every str is gotten before the
+/// // cache is locked.
+/// assert_eq!(intern_all(&ids), ids);
+/// ```
+#[inline]
+#[track_caller]
+pub fn intern_all<S: AsRef<str>>(items: &[S]) -> Vec<StrID> {
+  let mut ids = Vec::with_capacity(items.len());
+  intern_batch(items, |id| ids.push(id));
+  ids
+}
+
+/// Interns each word of a string, giving back their IDs in order.
+///
+/// The words are split up with [str::split_whitespace], so they're separated
+/// by any amount of Unicode whitespace, and there are never any empty words.
+/// A string that's empty (or all whitespace) gives an empty `Vec`. Like
+/// [intern_all], this takes the write lock just once for all of the words.
+///
+/// ```
+/// # use str_id::{intern_words, StrID};
+/// let ids = intern_words("the cat  sat\non the mat");
+/// assert_eq!(ids.len(), 6);
+/// assert_eq!(ids[1], "cat");
+/// assert_eq!(ids[0], ids[4]);
+/// ```
+#[inline]
+#[track_caller]
+pub fn intern_words(s: &str) -> Vec<StrID> {
+  let mut ids = Vec::new();
+  intern_batch(s.split_whitespace(), |id| ids.push(id));
+  ids
+}
+
+/// Interns each part of a string between the `separator` chars, giving back
+/// their IDs in order.
+///
+/// The string is split up with [str::split], so unlike [intern_words], empty
+/// parts are kept, and an empty string has one (empty) part. This takes the
+/// write lock just once for all of the parts.
+///
+/// ```
+/// # use str_id::intern_split;
+/// let ids = intern_split("a,b,,c", ',');
+/// assert_eq!(ids, ["a", "b", "", "c"]);
+/// ```
+#[inline]
+#[track_caller]
+pub fn intern_split(s: &str, separator: char) -> Vec<StrID> {
+  let mut ids = Vec::new();
+  intern_batch(s.split(separator), |id| ids.push(id));
+  ids
+}
+
+/// Interns a fixed set of strings before anything else, so that they get the
+/// same IDs in every run of the program.
+///
+/// This is for tools whose output has to be byte-for-byte reproducible. The
+/// strings are sorted (and deduplicated) first, so the order of the slice
+/// doesn't matter, only which strings are in it. Those strings then always
+/// get the first ID values, in sorted order (without sharding that's the
+/// values 1 through N, with sharding the values are spread out, but they're
+/// still the same every run).
+///
+/// **This only pins down the strings in the slice.** Anything interned
+/// afterwards gets an ID past those, which can differ between runs as usual.
+///
+/// This must be called before any other string is interned (or a snapshot is
+/// imported). It gives `true` if the strings were interned, or `false` if
+/// the cache had already given out an ID, in which case nothing is done.
+///
+/// ```
+/// # use str_id::{init_deterministic, StrID};
+/// assert!(init_deterministic(&["beta", "alpha"]));
+/// assert!(StrID::from("alpha") < StrID::from("beta"));
+/// assert!(!init_deterministic(&["gamma"]));
+/// ```
+#[inline]
+#[track_caller]
+pub fn init_deterministic(strings: &[&str]) -> bool {
+  let mut sorted = strings.to_vec();
+  sorted.sort_unstable();
+  sorted.dedup();
+  let mut shards = write_all();
+  // every shard is locked, so no other thread can give out an ID between the
+  // check and the interning.
+  if NEXT_STR_ID.load(Ordering::Relaxed) != 1 {
+    return false;
+  }
+  let (inserted, result) = intern_batch_locked(&mut shards, &sorted, |_| ());
+  drop(shards);
+  notify_interned(&inserted);
+  if result.is_err() {
+    exhausted()
+  }
+  true
+}
+
+/// How many items [preload] reads from its iterator before interning them.
+const PRELOAD_CHUNK: usize = 1024;
+
+/// Interns every string from an iterator, without giving back the IDs.
+///
+/// This is for warming up the cache when the program starts, such as from a
+/// list of strings that's embedded in the program or read from a file. Like
+/// [intern_all], equal strings get equal IDs. Calling [reserve] first (if you
+/// know about how many strings there are) avoids growing the cache over and
+/// over.
+///
+/// The iterator is read 1024 items at a time, and each of those chunks is
+/// interned with the write lock taken just once, so only one chunk is ever
+/// buffered. Each chunk is read before the lock is taken, so the iterator can
+/// use the cache itself (the items can even be [StrID] values).
+///
+/// ```
+/// # use str_id::{preload, StrID};
+/// let ids = [StrID::from("a"), StrID::from("b")];
+/// preload(ids.iter().map(|id| id.as_str()).chain(["c"]));
+/// preload(ids);
+/// assert_eq!(StrID::get("c"), Some(StrID::from("c")));
+///
+/// // a long iterator is interned as it's read, not collected all at once.
+/// preload((0..5000).map(|i| format!("line {i}")));
+/// assert!(StrID::get("line 4999").is_some());
+/// ```
+#[inline]
+#[track_caller]
+pub fn preload<I>(iter: I)
+where
+  I: IntoIterator,
+  I::Item: AsRef<str>,
+{
+  let mut iter = iter.into_iter();
+  loop {
+    let chunk: Vec<I::Item> = iter.by_ref().take(PRELOAD_CHUNK).collect();
+    if chunk.is_empty() {
+      break;
+    }
+    intern_batch(chunk, |_| ());
+  }
+}
+
+/// Interns a batch of strings with every shard write locked for the whole
+/// batch, passing each ID to `each` in order.
+#[inline]
+#[track_caller]
+fn intern_batch<I>(items: I, each: impl FnMut(StrID))
+where
+  I: IntoIterator,
+  I::Item: AsRef<str>,
+{
+  // Everything is turned into a str before locking. Getting the str of an
+  // item can use the cache itself (a `StrID` is `AsRef<str>`), which would
+  // deadlock under the write locks, and the caller's iterator could too.
+  let items: Vec<I::Item> = items.into_iter().collect();
+  let strs: Vec<&str> = items.iter().map(AsRef::as_ref).collect();
+  let mut shards = write_all();
+  let (inserted, result) = intern_batch_locked(&mut shards, &strs, each);
+  drop(shards);
+  notify_interned(&inserted);
+  if result.is_err() {
+    exhausted()
+  }
+}
+
+/// Like [intern_batch], for when every shard is already write locked.
+///
+/// This gives back the newly inserted IDs, which the caller must pass to
+/// [notify_interned] once the locks are released. If the ID values run out,
+/// this stops there and also gives back the error, which the caller must only
+/// panic with once the locks are released, so that the locks aren't poisoned
+/// when nothing was left half inserted.
+#[inline]
+fn intern_batch_locked(
+  shards: &mut [WriteGuard], strs: &[&str], mut each: impl FnMut(StrID),
+) -> (Vec<StrID>, Result<(), StrIDExhausted>) {
+  let mut inserted = Vec::new();
+  for s in strs.iter().copied() {
+    #[cfg(feature = "normalize")]
+    let s: &str = &nfc(s);
+    let shard = shard_of_str(s);
+    match StrID::try_intern_locked(&mut shards[shard], shard, s) {
+      Ok((id, is_new)) => {
+        count_intern(is_new);
+        each(id);
+        if is_new {
+          inserted.push(id);
+        }
+      }
+      Err(e) => return (inserted, Err(e)),
+    }
+  }
+  (inserted, Ok(()))
+}
+
+/// Interns each component of a path, giving back their IDs in order.
+///
+/// The path is split up with [Path::components](std::path::Path::components),
+/// which also tidies the path a little: repeated separators and `.`
+/// components (other than a leading one) are skipped, and so is a trailing
+/// separator. A root directory component is interned as the separator string
+/// (`"/"` on Unix), and on Windows a prefix like `C:` is interned as it's
+/// written. An empty path has no components, so it gives an empty `Vec`.
+///
+/// Components that aren't valid UTF-8 give `None`, and the rest of the
+/// components are still interned.
+///
+/// ```
+/// # use str_id::{intern_path_components, StrID};
+/// # use std::path::Path;
+/// let ids = intern_path_components(Path::new("usr//./lib/"));
+/// assert_eq!(ids, [Some(StrID::from("usr")), Some(StrID::from("lib"))]);
+/// assert!(intern_path_components(Path::new("")).is_empty());
+/// ```
+#[cfg(feature = "std")]
+#[inline]
+#[track_caller]
+pub fn intern_path_components(path: &std::path::Path) -> Vec<Option<StrID>> {
+  path
+    .components()
+    .map(|component| StrID::try_from_os_str(component.as_os_str()))
+    .collect()
+}
+
+/// Interns the concatenation of all the parts.
+///
+/// When the combined length is short enough the concatenation is built on the
+/// stack, so that nothing is allocated unless the result does have to be
+/// inserted into the cache. Longer results are built in a `String`.
+///
+/// ```
+/// # use str_id::{intern_concat, StrID};
+/// assert_eq!(intern_concat(&["a", "b"]), StrID::from("ab"));
+/// // it's the same ID however the parts are split up.
+/// let id = intern_concat(&["module", "::", "name"]);
+/// assert_eq!(id, "module::name");
+/// assert_eq!(intern_concat(&["module::", "name"]), id);
+///
+/// // with no parts, or only empty parts, it's the empty string.
+/// assert_eq!(intern_concat(&[]), "");
+/// assert_eq!(intern_concat(&["", ""]), intern_concat(&[]));
+///
+/// // past 128 bytes the concatenation is built in a `String` instead.
+/// let half = "x".repeat(100);
+/// let long = intern_concat(&[&half, &half]);
+/// assert_eq!(long.len(), 200);
+/// assert_eq!(long, StrID::from("x".repeat(200)));
+/// ```
+#[inline]
+#[track_caller]
+pub fn intern_concat(parts: &[&str]) -> StrID {
+  let total: usize = parts.iter().map(|part| part.len()).sum();
+  let mut buf = [0_u8; 128];
+  if total <= buf.len() {
+    let mut end = 0;
+    for part in parts {
+      buf[end..end + part.len()].copy_from_slice(part.as_bytes());
+      end += part.len();
+    }
+    let s = core::str::from_utf8(&buf[..end])
+      .expect("the concatenation of str slices is always valid UTF-8");
+    StrID::intern(s)
+  } else {
+    StrID::intern(parts.concat())
+  }
+}
+
+/// Sets the counter that new ID values are made from.
+///
+/// This is only available with the `testing` feature, and exists so that
+/// tests can check what happens as the ID values run out, without interning
+/// billions of strings first. Setting the counter to 0 marks the ID values as
+/// used up. See [next_id] for how the counter relates to the ID values.
+///
+/// **Setting the counter lower than it was makes new strings get IDs that are
+/// already in use.** Only do this right after [reset_cache], or to a higher
+/// value than [next_id] gives.
+///
+/// ```
+/// # use str_id::{set_next_id, shard_count, StrID};
+/// set_next_id(StrID::MAX.as_usize() / shard_count());
+/// assert!(StrID::try_from_str("the last one").is_ok());
+/// assert!(StrID::try_from_str("no more").is_err());
+/// // it stays exhausted, instead of wrapping around to reuse IDs.
+/// assert!(StrID::try_from_str("still no more").is_err());
+/// ```
+///
+/// Running out partway through a batch panics, but only once the cache's
+/// locks are released, so they aren't poisoned.
+///
+/// ```
+/// # use str_id::{intern_all, is_poisoned, set_next_id, shard_count, StrID};
+/// set_next_id(StrID::MAX.as_usize() / shard_count());
+/// let result = std::panic::catch_unwind(|| intern_all(&["last", "too many"]));
+/// assert!(result.is_err());
+/// assert!(!is_poisoned());
+/// assert_eq!(StrID::get("last").unwrap(), "last");
+/// ```
+#[cfg(feature = "testing")]
+#[inline]
+pub fn set_next_id(n: usize) {
+  #[cfg(feature = "small-id")]
+  let n = IdInt::try_from(n).unwrap_or(0);
+  NEXT_STR_ID.store(n as IdInt, Ordering::Relaxed);
+}
+
+/// Empties the cache and starts handing out ID values from the beginning
+/// again.
+///
+/// This is only available with the `testing` feature, and exists so that
+/// tests which depend on specific ID values, on [interned_count], or on
+/// [intern_stats] can be made deterministic. The cache keeps the number of
+/// shards and the hasher that it was set up with.
+///
+/// **Every `StrID` obtained before the reset is invalidated.** They'll look
+/// up as an empty string, or worse, as whatever string happens to get their
+/// ID value after the reset. The interned string data of the old cache is
+/// not freed. If other threads are interning strings while this runs, they
+/// might see the old cache or the new one, so this should only be called when
+/// nothing else is using the cache.
+#[cfg(feature = "testing")]
+pub fn reset_cache() {
+  let hasher = STR_CACHE.get().map_or_else(HasherKind::default, |c| c.hasher);
+  let mut shards = write_all();
+  for shard in shards.iter_mut() {
+    **shard = new_map(hasher);
+  }
+  NEXT_STR_ID.store(1, Ordering::Relaxed);
+  HITS.store(0, Ordering::Relaxed);
+  MISSES.store(0, Ordering::Relaxed);
+  EMPTY_ID.store(0, Ordering::Relaxed);
+  #[cfg(feature = "tracing")]
+  TRACED_COUNT.store(0, Ordering::Relaxed);
+  #[cfg(feature = "arc-swap")]
+  reverse_table::clear();
+  #[cfg(feature = "thread-local-cache")]
+  local_cache::invalidate_all();
+}
+
+/// Puts a string into Unicode NFC form, only allocating if that changes it.
+#[cfg(feature = "unicode-normalization")]
+fn nfc(s: &str) -> Cow<'_, str> {
+  use unicode_normalization::{IsNormalized, UnicodeNormalization};
+  if unicode_normalization::is_nfc_quick(s.chars()) == IsNormalized::Yes {
+    return Cow::Borrowed(s);
+  }
+  let normalized: String = s.nfc().collect();
+  if normalized == s { Cow::Borrowed(s) } else { Cow::Owned(normalized) }
+}
+
+/// A string value that can be interned.
+///
+/// When the string does have to be inserted into the cache it's first
+/// prepared, which for most types means boxing it (if necessary), and then
+/// the box is leaked to get a `&'static str`.
+trait Internable: AsRef<str> {
+  fn prepare(self) -> Prepared;
+}
+impl Internable for &str {
+  #[inline]
+  fn prepare(self) -> Prepared {
+    Prepared::Boxed(self.into())
+  }
+}
+impl Internable for String {
+  #[inline]
+  fn prepare(self) -> Prepared {
+    Prepared::Boxed(self.into_boxed_str())
+  }
+}
+impl Internable for Box<str> {
+  #[inline]
+  fn prepare(self) -> Prepared {
+    Prepared::Boxed(self)
+  }
+}
+impl Internable for Cow<'_, str> {
+  #[inline]
+  fn prepare(self) -> Prepared {
+    Prepared::Boxed(self.into())
+  }
+}
+
+/// A string that's already `'static`, so it can go in the cache as is.
+struct Literal(StaticStr);
+impl AsRef<str> for Literal {
+  #[inline]
+  fn as_ref(&self) -> &str {
+    self.0
+  }
+}
+impl Internable for Literal {
+  #[inline]
+  fn prepare(self) -> Prepared {
+    Prepared::Static(self.0)
+  }
+}
+
+/// A string that's ready to be inserted into the cache.
+///
+/// Nothing has been leaked yet, so if it turns out that the string doesn't
+/// need to be inserted after all, dropping this frees it.
+enum Prepared {
+  Boxed(Box<str>),
+  Static(StaticStr),
+}
+impl Prepared {
+  #[inline]
+  fn into_static(self) -> StaticStr {
+    match self {
+      Self::Boxed(b) => Box::leak(b),
+      Self::Static(s) => s,
+    }
+  }
+}
+impl AsRef<str> for Prepared {
+  #[inline]
+  fn as_ref(&self) -> &str {
+    match self {
+      Self::Boxed(b) => b,
+      Self::Static(s) => s,
+    }
+  }
+}
+impl Internable for Prepared {
+  #[inline]
+  fn prepare(self) -> Prepared {
+    self
+  }
+}
+
+#[cold]
+#[track_caller]
+fn exhausted() -> ! {
+  if cfg!(feature = "small-id") {
+    panic!("exhausted the available 32-bit StrID values!")
+  } else {
+    panic!("exhausted the available StrID values!")
+  }
+}
+
+/// This is a newtype over a [NonZeroUsize] which can get back the str slice
+/// used to obtain this ID.
+///
+/// With the `serde` feature this serializes as its string, which stays
+/// correct across program runs. See `StrIDRaw` for serializing the raw value.
+/// Likewise, with the `rkyv` feature this archives as an `ArchivedString`,
+/// and the raw ID value is never archived.
+///
+/// An ID value is never zero, so `Option<StrID>` is guaranteed to be the same
+/// size as `StrID`, with `None` stored as zero. With the `bytemuck` feature,
+/// `StrID` is `NoUninit`, so a slice of IDs can be viewed as bytes (but not
+/// the other way around, since zero isn't a valid ID).
+///
+/// ```
+/// # use str_id::StrID;
+/// # use core::mem::size_of;
+/// assert_eq!(size_of::<Option<StrID>>(), size_of::<StrID>());
+/// ```
+///
+/// Hashing, equality, and ordering all use the ID value rather than the string
+/// data, which keeps them cheap. This is also why `StrID` can't implement
+/// `Borrow<str>`: that trait requires the `Hash` and `Eq` of the borrowed form
+/// to agree with the owned form, and hashing a `usize` doesn't give the same
+/// result as hashing the string it stands for. A `HashMap<StrID, V>` can still
+/// be looked up from a `&str` with `map.get(&StrID::get(s)?)`, which also
+/// avoids inserting the string in the cache. Or use [ContentKey] as the map's
+/// key type, which does hash the string, and so can implement `Borrow<str>`.
+///
+/// Since IDs are handed out in order, sorting IDs (or using them as
+/// `BTreeMap` keys) gives roughly the order that the strings were first
+/// interned in, not alphabetical order. That's the right choice when any
+/// consistent order will do, because comparing two IDs never touches the
+/// cache. To sort by the strings themselves, wrap the IDs in [ByContent].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct StrID(IdValue);
+impl StrID {
+  /// The largest possible ID value.
+  pub const MAX: Self = Self(IdValue::MAX);
+
+  /// Allocates a new ID value for the given shard.
+  #[inline]
+  #[allow(clippy::unnecessary_cast)]
+  fn try_new(shard: usize) -> Option<Self> {
+    // Once the counter wraps around to 0 it has to stay there, otherwise it
+    // would start handing out IDs that are already in use.
+    let n = NEXT_STR_ID
+      .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+        if n == 0 { None } else { Some(n.wrapping_add(1)) }
+      })
+      .ok()?;
+    let n = IdValue::new(n)?;
+    let shards = cache().len() as IdInt;
+    let v = n.get().checked_mul(shards)?.checked_add(shard as IdInt)?;
+    IdValue::new(v).map(Self)
+  }
+
+  /// Gets the ID of a string, inserting it into the cache if necessary.
+  ///
+  /// The value is only turned into a `&'static str` (usually by leaking it)
+  /// when the string isn't already in the cache.
+  #[inline]
+  fn try_intern<T>(value: T) -> Result<Self, StrIDExhausted>
+  where
+    T: Internable,
+  {
+    Self::try_intern_reporting(value).map(|(id, _)| id)
+  }
+
+  /// Like [try_intern](Self::try_intern), but also says if the string was
+  /// newly inserted into the cache.
+  #[inline]
+  fn try_intern_reporting<T>(value: T) -> Result<(Self, bool), StrIDExhausted>
+  where
+    T: Internable,
+  {
+    #[cfg(feature = "normalize")]
+    if let Cow::Owned(normalized) = nfc(value.as_ref()) {
+      return Self::try_intern_reporting(normalized);
+    }
+    #[cfg(feature = "thread-local-cache")]
+    {
+      if let Some((id, _)) = local_cache::get(value.as_ref()) {
+        count_intern(false);
+        return Ok((id, false));
+      }
+      let (id, inserted) = Self::try_intern_shared(value)?;
+      local_cache::insert(id, id.as_str());
+      count_intern(inserted);
+      Ok((id, inserted))
+    }
+    #[cfg(not(feature = "thread-local-cache"))]
+    {
+      let (id, inserted) = Self::try_intern_shared(value)?;
+      count_intern(inserted);
+      Ok((id, inserted))
+    }
+  }
+
+  /// Like [try_intern_reporting](Self::try_intern_reporting), but always goes
+  /// to the global cache.
+  fn try_intern_shared<T>(value: T) -> Result<(Self, bool), StrIDExhausted>
+  where
+    T: Internable,
+  {
+    let s: &str = value.as_ref();
+    let shard = shard_of_str(s);
+    let rw_lock = &cache()[shard];
+    let read = read_lock(rw_lock);
+    if let Some(id) = read.get_by_right(s) {
+      Ok((*id, false))
+    } else {
+      drop(read);
+      // Any allocating is done before taking the write lock, so that the lock
+      // is held for as short a time as possible.
+      let prepared = value.prepare();
+      #[cfg(feature = "test-seams")]
+      write_lock_gap();
+      let mut write = write_lock(rw_lock);
+      // It's *possible* that the string was inserted after we dropped the
+      // reader before we acquired the writer, so we must check again. If it
+      // was, the prepared string is just dropped.
+      let (id, inserted) =
+        Self::try_intern_locked(&mut write, shard, prepared)?;
+      drop(write);
+      if inserted {
+        notify_interned(&[id]);
+      }
+      Ok((id, inserted))
+    }
+  }
+
+  /// Like [try_intern_reporting](Self::try_intern_reporting), for when the
+  /// write lock of the string's shard is already held.
+  fn try_intern_locked<T>(
+    write: &mut BiMap, shard: usize, value: T,
+  ) -> Result<(Self, bool), StrIDExhausted>
+  where
+    T: Internable,
+  {
+    if let Some(id) = write.get_by_right(value.as_ref()) {
+      Ok((*id, false))
+    } else {
+      let id: StrID = StrID::try_new(shard).ok_or(StrIDExhausted)?;
+      let leaked: StaticStr = value.prepare().into_static();
+      write.insert(id, leaked);
+      #[cfg(feature = "arc-swap")]
+      reverse_table::insert(id, leaked);
+      Ok((id, true))
+    }
+  }
+
+  #[inline]
+  #[track_caller]
+  fn intern<T>(value: T) -> Self
+  where
+    T: Internable,
+  {
+    match Self::try_intern(value) {
+      Ok(id) => id,
+      Err(_) => exhausted(),
+    }
+  }
+
+  /// Interns a `&'static str`.
+  ///
+  /// If the string isn't already in the cache, the reference itself is stored
+  /// in the cache, instead of a leaked copy of the data. If an equal string is
+  /// already in the cache, the existing ID is given back as usual, and the
+  /// reference is just not used. Either way nothing is leaked, and equal
+  /// literals from different places (or different crates) share one entry.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let a = StrID::from(String::from("x"));
+  /// let b = StrID::from_static("x");
+  /// assert_eq!(a, b);
+  /// assert_eq!(b.as_str().as_ptr(), a.as_str().as_ptr());
+  ///
+  /// // a new string is stored as the reference that it was given.
+  /// let s: &'static str = "only ever static";
+  /// assert_eq!(StrID::from_static(s).as_str().as_ptr(), s.as_ptr());
+  /// ```
+  #[inline]
+  #[track_caller]
+  pub fn from_static(s: &'static str) -> Self {
+    Self::intern(Literal(s))
+  }
+
+  /// Interns the Unicode NFC form of a str slice.
+  ///
+  /// Canonically equivalent strings, such as an accented letter written as one
+  /// precomposed character or as a letter and a combining accent, all get the
+  /// same ID from this, and [as_str](Self::as_str) gives back the NFC form.
+  /// Note that a string which isn't in NFC form still gets its own, different,
+  /// ID when it's interned by the other methods (unless the `normalize`
+  /// feature is enabled, in which case all interning works like this).
+  ///
+  /// Nothing extra is allocated when the string is already in NFC form.
+  #[cfg(feature = "unicode-normalization")]
+  #[inline]
+  #[track_caller]
+  pub fn from_normalized(s: &str) -> Self {
+    Self::intern(nfc(s))
+  }
+
+  /// Interns the ASCII lowercase form of a str slice.
+  ///
+  /// This is for things like HTTP header names and keywords, where
+  /// `"Content-Type"` and `"content-type"` should be the same ID. The stored
+  /// string is the lowercased form, so [as_str](Self::as_str) always gives
+  /// that back. Only ASCII letters are changed, any other characters are kept
+  /// as they are.
+  ///
+  /// When the string has no ASCII uppercase letters this is the same as
+  /// interning it normally. Otherwise it gets lowercased into a new buffer,
+  /// which is leaked if the lowercase form isn't already in the cache.
+  #[inline]
+  #[track_caller]
+  pub fn from_ascii_case_insensitive(s: &str) -> Self {
+    if s.bytes().any(|b| b.is_ascii_uppercase()) {
+      Self::intern(s.to_ascii_lowercase())
+    } else {
+      Self::intern(s)
+    }
+  }
+
+  /// Interns the Unicode case folded form of a str slice.
+  ///
+  /// This is like
+  /// [from_ascii_case_insensitive](Self::from_ascii_case_insensitive), but
+  /// for all of Unicode instead of just ASCII. It uses the full default
+  /// case folding from the Unicode standard (the `C` and `F` mappings of
+  /// `CaseFolding.txt`, without the Turkic `T` mappings), so for example
+  /// `"STRASSE"` and `"straße"` both become `"strasse"`. The stored string is
+  /// the folded form, so [as_str](Self::as_str) gives that back.
+  ///
+  /// Folding doesn't normalize the string, so canonically equivalent strings
+  /// can still fold to different strings. See `from_normalized` (with the
+  /// `unicode-normalization` feature) for that.
+  ///
+  /// This needs the `unicode-case` feature. Nothing extra is allocated when
+  /// the string is already case folded.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let a = StrID::from_case_folded("STRASSE");
+  /// assert_eq!(a, StrID::from_case_folded("straße"));
+  /// assert_eq!(StrID::from_case_folded("Straße"), "strasse");
+  /// ```
+  #[cfg(feature = "unicode-case")]
+  #[inline]
+  #[track_caller]
+  pub fn from_case_folded(s: &str) -> Self {
+    use caseless::Caseless;
+    if s.chars().default_case_fold().eq(s.chars()) {
+      Self::intern(s)
+    } else {
+      Self::intern(s.chars().default_case_fold().collect::<String>())
+    }
+  }
+
+  /// Interns a str slice with the leading and trailing whitespace removed.
+  ///
+  /// Whitespace is as defined by [str::trim], so this is Unicode whitespace,
+  /// not just ASCII. The trimmed string is a subslice of `s`, so nothing is
+  /// allocated when it's already in the cache.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// assert_eq!(StrID::from_trimmed("  foo \n"), StrID::from("foo"));
+  /// ```
+  #[inline]
+  #[track_caller]
+  pub fn from_trimmed(s: &str) -> Self {
+    Self::intern(s.trim())
+  }
+
+  /// Interns a str slice with its whitespace tidied up.
+  ///
+  /// Leading and trailing whitespace is removed, and each run of whitespace
+  /// inside the string becomes a single space, so `"foo   bar\n"` and
+  /// `"foo bar"` get the same ID. Whitespace is as defined by
+  /// [char::is_whitespace], the same as for [str::trim] and
+  /// [str::split_whitespace], so this includes non-ASCII whitespace such as
+  /// U+00A0 NO-BREAK SPACE.
+  ///
+  /// When the string is already tidy (apart from leading and trailing
+  /// whitespace), nothing is allocated. Otherwise the tidy string is built
+  /// once, and leaked if it's not already in the cache.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let id = StrID::from_collapsed_whitespace(" foo \t\n bar ");
+  /// assert_eq!(id, "foo bar");
+  /// ```
+  #[inline]
+  #[track_caller]
+  pub fn from_collapsed_whitespace(s: &str) -> Self {
+    let trimmed = s.trim();
+    let mut prev_space = false;
+    let tidy = trimmed.chars().all(|c| {
+      let ok = if c == ' ' { !prev_space } else { !c.is_whitespace() };
+      prev_space = c == ' ';
+      ok
+    });
+    if tidy {
+      Self::intern(trimmed)
+    } else {
+      let mut out = String::with_capacity(trimmed.len());
+      for word in trimmed.split_whitespace() {
+        if !out.is_empty() {
+          out.push(' ');
+        }
+        out.push_str(word);
+      }
+      Self::intern(out)
+    }
+  }
+
+  /// Interns an `OsStr`, if it's valid UTF-8.
+  ///
+  /// This gives `None` when the `OsStr` isn't valid UTF-8, rather than doing
+  /// a lossy conversion. Use [from_os_str_lossy](Self::from_os_str_lossy) if
+  /// a lossy conversion is what you want.
+  #[cfg(feature = "std")]
+  #[inline]
+  #[track_caller]
+  pub fn try_from_os_str(s: &std::ffi::OsStr) -> Option<Self> {
+    s.to_str().map(Self::intern)
+  }
+
+  /// Interns a whole path as one string, if it's valid UTF-8.
+  ///
+  /// The path is interned exactly as it's written, without being split up or
+  /// tidied. To intern each part of the path on its own, use
+  /// [intern_path_components].
+  #[cfg(feature = "std")]
+  #[inline]
+  #[track_caller]
+  pub fn try_from_path(path: &std::path::Path) -> Option<Self> {
+    Self::try_from_os_str(path.as_os_str())
+  }
+
+  /// Interns an `OsStr`, replacing any invalid UTF-8 with
+  /// [U+FFFD](char::REPLACEMENT_CHARACTER).
+  ///
+  /// Different `OsStr` values can end up as the same string this way, and so
+  /// get the same ID.
+  #[cfg(feature = "std")]
+  #[inline]
+  #[track_caller]
+  pub fn from_os_str_lossy(s: &std::ffi::OsStr) -> Self {
+    Self::intern(s.to_string_lossy())
+  }
+
+  /// Interns a formatted string, such as from `format_args!`.
+  ///
+  /// This is like interning the result of `format!`, but the string is
+  /// formatted into a `String` buffer that each thread keeps around and
+  /// reuses, so when the string is already interned nothing is allocated at
+  /// all. The buffer keeps the largest capacity that it has ever needed, so a
+  /// thread that formats one very long string keeps that much memory until the
+  /// thread exits. When the arguments are just a literal, with nothing to
+  /// format, the literal is interned with [from_static](Self::from_static).
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let (a, b) = ("foo", "bar");
+  /// let id = StrID::from_fmt(format_args!("{a}::{b}"));
+  /// assert_eq!(id, "foo::bar");
+  /// ```
+  ///
+  /// ## Panics
+  /// * If a formatting trait implementation gives an error, just like
+  ///   `format!`.
+  #[cfg(feature = "std")]
+  #[inline]
+  #[track_caller]
+  pub fn from_fmt(args: core::fmt::Arguments<'_>) -> Self {
+    use core::{cell::RefCell, fmt::Write};
+    std::thread_local! {
+      static BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
+    }
+    if let Some(s) = args.as_str() {
+      return Self::from_static(s);
+    }
+    BUFFER.with(|buffer| match buffer.try_borrow_mut() {
+      Ok(mut buffer) => {
+        buffer.clear();
+        buffer
+          .write_fmt(args)
+          .expect("a formatting trait implementation returned an error");
+        let id = Self::intern(buffer.as_str());
+        buffer.clear();
+        id
+      }
+      // the arguments themselves called `from_fmt`, so the buffer is in use.
+      Err(_) => Self::intern(std::fmt::format(args)),
+    })
+  }
+
+  /// Gets the ID of the empty string.
+  ///
+  /// The empty string is interned the first time that this is called (if it
+  /// wasn't interned already), and after that the ID is remembered, so this
+  /// doesn't need to look in the cache at all. The ID is the same for the
+  /// rest of the program run, but which ID it is depends on when the empty
+  /// string was first interned, so it can differ between runs.
+  #[inline]
+  #[track_caller]
+  pub fn empty() -> Self {
+    if let Some(v) = IdValue::new(EMPTY_ID.load(Ordering::Relaxed)) {
+      return Self(v);
+    }
+    let id = Self::from_static("");
+    EMPTY_ID.store(id.0.get(), Ordering::Relaxed);
+    id
+  }
+
+  /// Interns `s` repeated `n` times.
+  ///
+  /// This is for building strings like separators and indentation. When the
+  /// repeated string is short (at most 128 bytes) it's built on the stack, so
+  /// if it's already interned nothing is allocated. A longer string is built
+  /// with [str::repeat]. Repeating a string 0 times, or repeating the empty
+  /// string, gives [empty](Self::empty).
+  ///
+  /// Note that `id.repeat(n)` on an existing ID is still [str::repeat]
+  /// through `Deref`, which gives a `String`.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// assert_eq!(StrID::repeat("  ", 3), "      ");
+  /// assert_eq!(StrID::repeat("-", 0), "");
+  /// ```
+  ///
+  /// ## Panics
+  /// * If the repeated string would be more than `isize::MAX` bytes long,
+  ///   which is too long to ever allocate.
+  #[inline]
+  #[track_caller]
+  pub fn repeat(s: &str, n: usize) -> Self {
+    const STACK_LEN: usize = 128;
+    if s.is_empty() || n == 0 {
+      return Self::empty();
+    }
+    let len = match s.len().checked_mul(n) {
+      Some(len) if len <= isize::MAX as usize => len,
+      _ => panic!("the repeated string is too long!"),
+    };
+    if len <= STACK_LEN {
+      let mut buf = [0_u8; STACK_LEN];
+      for chunk in buf[..len].chunks_exact_mut(s.len()) {
+        chunk.copy_from_slice(s.as_bytes());
+      }
+      let repeated = core::str::from_utf8(&buf[..len])
+        .expect("a repeated str is still valid UTF-8");
+      Self::intern(repeated)
+    } else {
+      Self::intern(s.repeat(n))
+    }
+  }
+
+  /// Interns a str slice, also saying if it was newly inserted into the cache.
+  ///
+  /// The `bool` is `true` when this call inserted (and leaked) the string, and
+  /// `false` when it was already in the cache. This is decided while holding
+  /// the write lock, so when several threads intern the same new string at
+  /// once, exactly one of them sees `true`.
+  #[inline]
+  #[track_caller]
+  pub fn intern_reporting(s: &str) -> (Self, bool) {
+    match Self::try_intern_reporting(s) {
+      Ok(pair) => pair,
+      Err(_) => exhausted(),
+    }
+  }
+
+  /// Interns a str slice, or gives an error if there are no ID values left.
+  ///
+  /// The `From` impls panic when the ID values run out, which this lets you
+  /// handle instead.
+  #[inline]
+  pub fn try_from_str(s: &str) -> Result<Self, StrIDExhausted> {
+    Self::try_intern(s)
+  }
+
+  /// Like [try_from_str](Self::try_from_str), but reuses the `String`'s
+  /// buffer if the string has to be inserted into the cache.
+  #[inline]
+  pub fn try_from_string(s: String) -> Result<Self, StrIDExhausted> {
+    Self::try_intern(s)
+  }
+
+  /// Like [try_from_str](Self::try_from_str), but leaks the box directly if
+  /// the string has to be inserted into the cache.
+  #[inline]
+  pub fn try_from_boxed_str(s: Box<str>) -> Result<Self, StrIDExhausted> {
+    Self::try_intern(s)
+  }
+
+  /// Interns the concatenation of `a` and `b`.
+  ///
+  /// This is a shorthand for [intern_concat] with two parts.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let id = StrID::concat("module", "::name");
+  /// assert_eq!(id, "module::name");
+  /// assert_eq!(StrID::concat("module::", "name"), id);
+  /// assert_eq!(StrID::from("module::name"), id);
+  /// assert_eq!(StrID::concat("", ""), "");
+  /// ```
+  #[inline]
+  #[track_caller]
+  pub fn concat(a: &str, b: &str) -> Self {
+    intern_concat(&[a, b])
+  }
+
+  /// Rebuilds an ID from a raw `usize` obtained via
+  /// [as_usize](Self::as_usize).
+  ///
+  /// This is only `Some` if the value is an ID that's actually in the cache.
+  /// Zero, as well as any value that hasn't been handed out (including values
+  /// larger than any ID allocated so far), gives `None`.
+  ///
+  /// IDs are only meaningful within the process that created them.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let id = StrID::from("round trip");
+  /// assert_eq!(StrID::try_from_usize(id.as_usize()), Some(id));
+  ///
+  /// assert_eq!(StrID::try_from_usize(0), None);
+  /// // this is the newest ID, so the next value hasn't been handed out.
+  /// assert_eq!(StrID::try_from_usize(id.as_usize() + 1), None);
+  /// assert_eq!(StrID::try_from_usize(usize::MAX), None);
+  /// ```
+  #[inline]
+  #[must_use]
+  pub fn try_from_usize(n: usize) -> Option<Self> {
+    #[cfg(feature = "small-id")]
+    let n: u32 = n.try_into().ok()?;
+    let id = Self(IdValue::new(n)?);
+    let read = read_lock(&cache()[shard_of_id(id)]);
+    if read.contains_left(&id) { Some(id) } else { None }
+  }
+
+  /// Wraps a raw value as an ID without checking the cache.
+  ///
+  /// This skips the read lock taken by
+  /// [try_from_usize](Self::try_from_usize), for when you've already
+  /// validated the value some other way. The caller is responsible for the
+  /// value having come from this process's cache. If it didn't, nothing
+  /// unsafe happens, but [as_str](Self::as_str) on the ID will give an empty
+  /// string (or panic, depending on the [MissingIdPolicy]), or some unrelated
+  /// string.
+  ///
+  /// With the `small-id` feature, a value that doesn't fit in 32 bits can't
+  /// have come from the cache. Debug builds panic on such a value, and release
+  /// builds clamp it to [StrID::MAX].
+  #[inline]
+  #[must_use]
+  pub const fn from_usize_unchecked(n: NonZeroUsize) -> Self {
+    #[cfg(not(feature = "small-id"))]
+    {
+      Self(n)
+    }
+    #[cfg(feature = "small-id")]
+    {
+      debug_assert!(
+        n.get() <= u32::MAX as usize,
+        "the value is too big to be a small-id StrID"
+      );
+      // values that can't be a 32-bit ID are clamped, which still gives an ID
+      // that's very likely not in the cache.
+      match IdValue::new(n.get() as u32) {
+        Some(v) if n.get() <= u32::MAX as usize => Self(v),
+        _ => Self(IdValue::MAX),
+      }
+    }
+  }
+
+  /// Unwraps the value into a raw `usize`.
+  #[inline]
+  #[must_use]
+  pub const fn as_usize(self) -> usize {
+    #[cfg(not(feature = "small-id"))]
+    {
+      self.0.get()
+    }
+    #[cfg(feature = "small-id")]
+    {
+      self.0.get() as usize
+    }
+  }
+
+  /// Unwraps the value into a raw [NonZeroUsize].
+  ///
+  /// This is [as_usize](Self::as_usize), but keeps the guarantee that the
+  /// value isn't zero, so that types built around the value (such as for FFI)
+  /// can still have a niche for `Option`.
+  #[inline]
+  #[must_use]
+  pub const fn as_nonzero(self) -> NonZeroUsize {
+    match NonZeroUsize::new(self.as_usize()) {
+      Some(n) => n,
+      // an ID value is never zero.
+      None => unreachable!(),
+    }
+  }
+
+  /// Wraps a raw [NonZeroUsize] as an ID without checking the cache.
+  ///
+  /// This is the counterpart of [as_nonzero](Self::as_nonzero), and it's the
+  /// same as [from_usize_unchecked](Self::from_usize_unchecked), so the same
+  /// caveats apply.
+  #[inline]
+  #[must_use]
+  pub const fn from_nonzero_unchecked(n: NonZeroUsize) -> Self {
+    Self::from_usize_unchecked(n)
+  }
+
+  /// Gets the str slice associated with this ID value.
+  ///
+  /// If the ID isn't in the cache this gives an empty string. An ID obtained
+  /// from this crate is always in the cache, so that can only happen with an
+  /// ID that was forged or carried over from some other process. Use
+  /// [try_as_str](Self::try_as_str) if you need to tell a missing ID apart
+  /// from an interned empty string.
+  ///
+  /// ## Panics
+  /// * If the ID isn't in the cache, and [set_missing_id_policy] has set the
+  ///   policy to [MissingIdPolicy::Panic].
+  #[inline]
+  #[must_use]
+  #[track_caller]
+  pub fn as_str(self) -> StaticStr {
+    match self.try_as_str() {
+      Some(s) => s,
+      None if PANIC_ON_MISSING_ID.load(Ordering::Relaxed) => {
+        panic!("StrID {} isn't in the cache!", self.as_usize())
+      }
+      None => "",
+    }
+  }
+
+  /// Gets the str slice associated with this ID value, or `None` if the ID
+  /// isn't in the cache.
+  ///
+  /// Unlike [as_str](Self::as_str), this distinguishes an interned empty
+  /// string (`Some("")`) from a missing ID (`None`).
+  #[inline]
+  #[must_use]
+  pub fn try_as_str(self) -> Option<StaticStr> {
+    #[cfg(feature = "arc-swap")]
+    if let Some(s) = reverse_table::get(self) {
+      return Some(s);
+    }
+    let read = read_lock(&cache()[shard_of_id(self)]);
+    read.get_by_left(&self).copied()
+  }
+
+  /// Copies the string into a new `String`.
+  ///
+  /// For when an API needs an owned string. Just like with
+  /// [as_str](Self::as_str), an ID that isn't in the cache gives an empty
+  /// string.
+  #[inline]
+  #[must_use]
+  pub fn to_string_owned(self) -> String {
+    String::from(self.as_str())
+  }
+
+  /// Gets the length of the string in bytes.
+  ///
+  /// Like [as_str](Self::as_str), an ID that isn't in the cache counts as the
+  /// empty string, so this is 0.
+  #[inline]
+  #[must_use]
+  pub fn len(self) -> usize {
+    self.as_str().len()
+  }
 
-use bimap::BiHashMap;
-use core::{
-  num::NonZeroUsize,
-  sync::atomic::{AtomicUsize, Ordering},
-};
-use std::sync::{OnceLock, PoisonError, RwLock};
+  /// Checks if the string is empty.
+  ///
+  /// This is also `true` for an ID that isn't in the cache.
+  #[inline]
+  #[must_use]
+  pub fn is_empty(self) -> bool {
+    self.as_str().is_empty()
+  }
 
-/// An easier name to type because you don't have to use non-letter characters.
-pub type StaticStr = &'static str;
+  /// Gets the number of chars in the string.
+  ///
+  /// Unlike [len](Self::len) this is an O(n) scan of the string. Note that a
+  /// char isn't always what a person would call a character, see
+  /// `grapheme_count` for that.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let id = StrID::from("héllo");
+  /// assert_eq!(id.len(), 6);
+  /// assert_eq!(id.char_count(), 5);
+  /// ```
+  #[inline]
+  #[must_use]
+  pub fn char_count(self) -> usize {
+    self.as_str().chars().count()
+  }
+
+  /// Gets the number of extended grapheme clusters in the string, which is
+  /// the number of characters that a person would count.
+  ///
+  /// This is an O(n) scan of the string, using `unicode-segmentation`. It's
+  /// only available with the `unicode-segmentation` feature.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// // two regional indicator symbols, which make one flag.
+  /// let id = StrID::from("\u{1F1EF}\u{1F1F5}");
+  /// assert_eq!(id.char_count(), 2);
+  /// assert_eq!(id.grapheme_count(), 1);
+  /// ```
+  #[cfg(feature = "unicode-segmentation")]
+  #[inline]
+  #[must_use]
+  pub fn grapheme_count(self) -> usize {
+    use unicode_segmentation::UnicodeSegmentation;
+    self.as_str().graphemes(true).count()
+  }
 
-#[cfg(not(feature = "fnv"))]
-type BiMap = BiHashMap<StrID, StaticStr>;
-#[cfg(feature = "fnv")]
-type BiMap =
-  BiHashMap<StrID, StaticStr, fnv::FnvBuildHasher, fnv::FnvBuildHasher>;
+  /// Gets the number of columns that the string takes up in a terminal.
+  ///
+  /// This is `UnicodeWidthStr::width` from `unicode-width`, so control
+  /// characters, and characters whose width is ambiguous, are counted the way
+  /// that crate counts them (ambiguous characters are 1 column, not the 2 of
+  /// a CJK context). It's an O(n) scan of the string, and it's only available
+  /// with the `unicode-width` feature.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let id = StrID::from("日本");
+  /// assert_eq!(id.char_count(), 2);
+  /// assert_eq!(id.display_width(), 4);
+  /// ```
+  #[cfg(feature = "unicode-width")]
+  #[inline]
+  #[must_use]
+  pub fn display_width(self) -> usize {
+    unicode_width::UnicodeWidthStr::width(self.as_str())
+  }
 
-static NEXT_STR_ID: AtomicUsize = AtomicUsize::new(1);
+  /// Gets the bytes of the string.
+  ///
+  /// The string data is never freed, so the slice is `'static`. An ID that
+  /// isn't in the cache gives an empty slice.
+  #[inline]
+  #[must_use]
+  pub fn as_bytes(self) -> &'static [u8] {
+    self.as_str().as_bytes()
+  }
 
-static STR_CACHE: OnceLock<RwLock<BiMap>> = OnceLock::new();
+  /// Checks if the string starts with `prefix`.
+  ///
+  /// Every string starts with the empty string.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let id = StrID::from("foobar");
+  /// assert!(id.starts_with("foo"));
+  /// assert!(id.ends_with("bar"));
+  /// assert!(id.contains("oba"));
+  /// assert!(id.starts_with("") && id.ends_with("") && id.contains(""));
+  /// assert!(StrID::from("").contains(""));
+  /// ```
+  #[inline]
+  #[must_use]
+  pub fn starts_with(self, prefix: &str) -> bool {
+    self.as_str().starts_with(prefix)
+  }
 
-/// This is a newtype over a [NonZeroUsize] which can get back the str slice
-/// used to obtain this ID.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-#[repr(transparent)]
-pub struct StrID(NonZeroUsize);
-impl StrID {
+  /// Checks if the string ends with `suffix`.
+  ///
+  /// Every string ends with the empty string.
+  #[inline]
+  #[must_use]
+  pub fn ends_with(self, suffix: &str) -> bool {
+    self.as_str().ends_with(suffix)
+  }
+
+  /// Checks if the string contains `needle`.
+  ///
+  /// Every string contains the empty string.
+  #[inline]
+  #[must_use]
+  pub fn contains(self, needle: &str) -> bool {
+    self.as_str().contains(needle)
+  }
+
+  /// Gets the byte offset of the first match of `needle` in the string.
+  ///
+  /// This is [str::find] with a `&str` pattern.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let id = StrID::from("a::b::c");
+  /// assert_eq!(id.find("::"), Some(1));
+  /// assert_eq!(id.rfind("::"), Some(4));
+  /// assert_eq!(id.find("/"), None);
+  /// ```
+  #[inline]
+  #[must_use]
+  pub fn find(self, needle: &str) -> Option<usize> {
+    self.as_str().find(needle)
+  }
+
+  /// Gets the byte offset of the last match of `needle` in the string.
+  ///
+  /// This is [str::rfind] with a `&str` pattern.
+  #[inline]
+  #[must_use]
+  pub fn rfind(self, needle: &str) -> Option<usize> {
+    self.as_str().rfind(needle)
+  }
+
+  /// Compares the strings of two IDs lexicographically (by byte value).
+  ///
+  /// Equal IDs always have equal strings, so that case is `Equal` right away,
+  /// without looking at the strings. For sorting, see [ByContent] instead.
+  #[inline]
+  #[must_use]
+  pub fn cmp_str(self, other: StrID) -> core::cmp::Ordering {
+    if self == other {
+      return core::cmp::Ordering::Equal;
+    }
+    self.as_str().cmp(other.as_str())
+  }
+
+  /// Checks if the strings of two IDs are equal, ignoring ASCII case.
+  ///
+  /// This is [str::eq_ignore_ascii_case], with a fast path for equal IDs.
   #[inline]
-  fn try_new() -> Option<Self> {
-    NonZeroUsize::new(NEXT_STR_ID.fetch_add(1, Ordering::Relaxed)).map(Self)
+  #[must_use]
+  pub fn eq_ignore_ascii_case(self, other: StrID) -> bool {
+    self == other || self.as_str().eq_ignore_ascii_case(other.as_str())
   }
 
+  /// Interns a byte range of this string, giving the ID of the substring.
+  ///
+  /// The interned string data is `'static`, so the substring is interned with
+  /// [from_static](Self::from_static), and nothing is ever allocated for the
+  /// string data itself.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let path = StrID::from("src/lib.rs");
+  /// assert_eq!(path.slice(0..3), "src");
+  /// ```
+  ///
+  /// ## Panics
+  /// * Just like indexing a `str`, if the range is out of bounds, or either
+  ///   end of the range isn't on a char boundary.
+  ///
+  /// ```should_panic
+  /// # use str_id::StrID;
+  /// // 'é' is two bytes long, so 1 isn't a char boundary.
+  /// StrID::from("é").slice(0..1);
+  /// ```
   #[inline]
+  #[must_use]
   #[track_caller]
-  fn new() -> Self {
-    Self::try_new().expect("exhausted the available StrID values!")
+  pub fn slice(self, range: core::ops::Range<usize>) -> Self {
+    Self::from_static(&self.as_str()[range])
   }
 
-  /// Unwraps the value into a raw `usize`.
+  /// Gets the ID of the lowercase form of this string.
+  ///
+  /// This uses the full Unicode conversion of [str::to_lowercase]. When the
+  /// string is already lowercase, this gives back `self`, and for ASCII
+  /// strings that's checked without allocating. Otherwise the lowercase
+  /// string is interned. Note that this takes the place of calling
+  /// `to_lowercase` on the `str` through `Deref`, which gives a `String`.
   #[inline]
   #[must_use]
-  pub const fn as_usize(self) -> usize {
-    self.0.get()
+  #[track_caller]
+  pub fn to_lowercase(self) -> Self {
+    let s = self.as_str();
+    if s.is_ascii() {
+      if s.bytes().any(|b| b.is_ascii_uppercase()) {
+        Self::intern(s.to_ascii_lowercase())
+      } else {
+        self
+      }
+    } else {
+      let lower = s.to_lowercase();
+      if lower == s { self } else { Self::intern(lower) }
+    }
   }
 
-  /// Gets the str slice associated with this ID value.
+  /// Gets the ID of the uppercase form of this string.
+  ///
+  /// This is just like [to_lowercase](Self::to_lowercase), but with
+  /// [str::to_uppercase].
   #[inline]
   #[must_use]
-  pub fn as_str(self) -> StaticStr {
-    let rw_lock = STR_CACHE.get_or_init(|| RwLock::new(BiMap::default()));
-    let read = rw_lock.read().unwrap_or_else(PoisonError::into_inner);
-    read.get_by_left(&self).unwrap_or(&"")
+  #[track_caller]
+  pub fn to_uppercase(self) -> Self {
+    let s = self.as_str();
+    if s.is_ascii() {
+      if s.bytes().any(|b| b.is_ascii_lowercase()) {
+        Self::intern(s.to_ascii_uppercase())
+      } else {
+        self
+      }
+    } else {
+      let upper = s.to_uppercase();
+      if upper == s { self } else { Self::intern(upper) }
+    }
+  }
+
+  /// Gets the ID of this string with every match of `from` replaced by `to`.
+  ///
+  /// This is [str::replace], so it's O(n) in the length of the string, and
+  /// the new string is allocated and interned whenever something actually
+  /// changes. When `from` doesn't appear in the string (or is the same as
+  /// `to`) this gives back `self` without allocating.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let path = StrID::from("a::b::c");
+  /// assert_eq!(path.replace("::", "."), "a.b.c");
+  /// assert_eq!(path.replace("/", "."), path);
+  /// ```
+  #[inline]
+  #[must_use]
+  #[track_caller]
+  pub fn replace(self, from: &str, to: &str) -> Self {
+    if from == to || !self.contains(from) {
+      return self;
+    }
+    Self::intern(self.as_str().replace(from, to))
+  }
+
+  /// Gets how many leading bytes this string has in common with another.
+  ///
+  /// The result is a count of bytes, not of chars, so it can end partway
+  /// through a multi-byte char. When both IDs are the same this is just the
+  /// length of the string.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let a = StrID::from("interned");
+  /// let b = StrID::from("internal");
+  /// assert_eq!(a.common_prefix_len(b), 6);
+  /// ```
+  #[inline]
+  #[must_use]
+  pub fn common_prefix_len(self, other: StrID) -> usize {
+    if self == other {
+      return self.len();
+    }
+    let a = self.as_bytes();
+    let b = other.as_bytes();
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+  }
+
+  /// Wraps the ID so that it's ordered by its string instead of its ID value.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// let mut ids = vec![StrID::from("b"), StrID::from("c"), StrID::from("a")];
+  /// ids.sort_by_key(StrID::by_content);
+  /// assert_eq!(ids, ["a", "b", "c"]);
+  /// ```
+  #[inline]
+  #[must_use]
+  pub fn by_content(&self) -> ByContent {
+    ByContent(*self)
+  }
+
+  /// Gets a wrapper that displays the string quoted and escaped.
+  ///
+  /// The plain `Display` form is just the string, which can be hard to read
+  /// in logs when the string has spaces in it, or is empty. This uses the
+  /// same quoting and escaping as `Debug` does for a `str`.
+  ///
+  /// ```
+  /// # use str_id::StrID;
+  /// assert_eq!(StrID::from("foo bar").quoted().to_string(), r#""foo bar""#);
+  /// assert_eq!(StrID::from("").quoted().to_string(), r#""""#);
+  /// assert_eq!(StrID::from("a\n").quoted().to_string(), r#""a\n""#);
+  /// ```
+  #[inline]
+  #[must_use]
+  pub fn quoted(self) -> Quoted {
+    Quoted(self)
+  }
+
+  /// Gets the ID of a str slice, if it has already been interned.
+  ///
+  /// This only ever takes the read lock, and it never inserts anything into
+  /// the cache, so a miss doesn't allocate or leak any memory.
+  #[inline]
+  #[must_use]
+  pub fn get(s: &str) -> Option<Self> {
+    #[cfg(feature = "normalize")]
+    let s: &str = &nfc(s);
+    let read = read_lock(&cache()[shard_of_str(s)]);
+    read.get_by_right(s).copied()
   }
 }
 
+/// Formats as the quoted string. The alternate form (`{:#?}`) also shows the
+/// ID value, as `StrID(42, "foo")`.
 impl core::fmt::Debug for StrID {
   #[inline]
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> core::fmt::Result {
-    core::fmt::Debug::fmt(&self.as_str(), f)
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    // this ignores the missing ID policy, so that debugging a bad ID can't
+    // panic.
+    let s = self.try_as_str().unwrap_or("");
+    if f.alternate() {
+      write!(f, "StrID({}, {:?})", self.as_usize(), s)
+    } else {
+      core::fmt::Debug::fmt(&s, f)
+    }
   }
 }
 
 impl core::fmt::Display for StrID {
   #[inline]
-  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> core::fmt::Result {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
     core::fmt::Display::fmt(&self.as_str(), f)
   }
 }
 
-impl<'a> From<Box<str>> for StrID {
+impl From<Box<str>> for StrID {
   #[inline]
+  #[track_caller]
   fn from(value: Box<str>) -> Self {
-    let s: &str = &*value;
-    let rw_lock = STR_CACHE.get_or_init(|| RwLock::new(BiMap::default()));
-    let read = rw_lock.read().unwrap_or_else(PoisonError::into_inner);
-    if let Some(id) = read.get_by_right(s) {
-      *id
-    } else {
-      drop(read);
-      let mut write = rw_lock.write().unwrap_or_else(PoisonError::into_inner);
-      // It's *possible* that the string was inserted after we dropped the
-      // reader before we acquired the writer, so we must check again.
-      if let Some(id) = write.get_by_right(s) {
-        *id
-      } else {
-        let id: StrID = StrID::new();
-        let leaked: StaticStr = Box::leak(value);
-        write.insert(id, leaked);
-        id
-      }
-    }
+    // the box can be leaked directly if it does have to be inserted into the
+    // cache.
+    Self::intern(value)
   }
 }
 
 impl<'a> From<&'a str> for StrID {
   #[inline]
+  #[track_caller]
   fn from(s: &'a str) -> Self {
-    // essentially the same as the `Box<str>` version, just that we have to box
-    // the data if it does have to be inserted into the cache.
-    let rw_lock = STR_CACHE.get_or_init(|| RwLock::new(BiMap::default()));
-    let read = rw_lock.read().unwrap_or_else(PoisonError::into_inner);
-    if let Some(id) = read.get_by_right(&s) {
-      *id
-    } else {
-      drop(read);
-      let mut write = rw_lock.write().unwrap_or_else(PoisonError::into_inner);
-      if let Some(id) = write.get_by_right(s) {
-        *id
-      } else {
-        let id: StrID = StrID::new();
-        let leaked: StaticStr = Box::leak(s.to_string().into_boxed_str());
-        write.insert(id, leaked);
-        id
-      }
-    }
+    // we have to box the data if it does have to be inserted into the cache.
+    Self::intern(s)
+  }
+}
+
+impl From<&String> for StrID {
+  /// Interns the contents of the `String`, the same as with a `&str`.
+  #[inline]
+  #[track_caller]
+  fn from(s: &String) -> Self {
+    Self::intern(s.as_str())
+  }
+}
+
+impl From<&Box<str>> for StrID {
+  /// Interns the contents of the `Box`, the same as with a `&str`.
+  #[inline]
+  #[track_caller]
+  fn from(s: &Box<str>) -> Self {
+    Self::intern(&**s)
+  }
+}
+
+impl TryFrom<&[u8]> for StrID {
+  type Error = core::str::Utf8Error;
+
+  /// Interns the bytes, if they're valid UTF-8.
+  #[inline]
+  #[track_caller]
+  fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+    core::str::from_utf8(bytes).map(Self::intern)
+  }
+}
+
+impl TryFrom<Vec<u8>> for StrID {
+  type Error = alloc::string::FromUtf8Error;
+
+  /// Interns the bytes, if they're valid UTF-8.
+  ///
+  /// Just like with a `String`, the buffer is reused if the string does have
+  /// to be inserted into the cache. On an error, the buffer can be taken back
+  /// out of the error.
+  #[inline]
+  #[track_caller]
+  fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+    String::from_utf8(bytes).map(Self::intern)
   }
 }
 
 impl From<String> for StrID {
   #[inline]
+  #[track_caller]
   fn from(s: String) -> Self {
-    // essentially the same as the `Box<str>` version, just that we have to
-    // convert String into Box<str> the data if it does have to be inserted into
-    // the cache (which might be free or it might be a reallocation).
-    let rw_lock = STR_CACHE.get_or_init(|| RwLock::new(BiMap::default()));
-    let read = rw_lock.read().unwrap_or_else(PoisonError::into_inner);
-    if let Some(id) = read.get_by_right(s.as_str()) {
-      *id
-    } else {
-      drop(read);
-      let mut write = rw_lock.write().unwrap_or_else(PoisonError::into_inner);
-      if let Some(id) = write.get_by_right(s.as_str()) {
-        *id
-      } else {
-        let id: StrID = StrID::new();
-        let leaked: StaticStr = Box::leak(s.into_boxed_str());
-        write.insert(id, leaked);
-        id
-      }
-    }
+    // we have to convert String into Box<str> if it does have to be inserted
+    // into the cache (which might be free or it might be a reallocation).
+    Self::intern(s)
+  }
+}
+
+/// Interns either kind of `Cow`, only allocating when the string has to be
+/// inserted into the cache.
+///
+/// ```
+/// # use str_id::StrID;
+/// # use std::borrow::Cow;
+/// let borrowed = StrID::from(Cow::Borrowed("cow a"));
+/// // an owned string that's inserted keeps its buffer.
+/// let b = String::from("cow b");
+/// let b_ptr = b.as_ptr();
+/// let owned = StrID::from(Cow::<str>::Owned(b));
+/// assert_eq!(owned.as_str().as_ptr(), b_ptr);
+///
+/// // each one finds the other's entry, and still points at the same data.
+/// let again = StrID::from(Cow::<str>::Owned(String::from("cow a")));
+/// assert_eq!(again, borrowed);
+/// assert_eq!(again.as_str().as_ptr(), borrowed.as_str().as_ptr());
+/// assert_eq!(StrID::from(Cow::Borrowed("cow b")), owned);
+/// assert_eq!(StrID::from(Cow::Borrowed("cow b")).as_str().as_ptr(), b_ptr);
+/// ```
+impl<'a> From<Cow<'a, str>> for StrID {
+  #[inline]
+  #[track_caller]
+  fn from(s: Cow<'a, str>) -> Self {
+    // if it does have to be inserted into the cache, an owned string reuses
+    // its buffer like the `String` version does, and a borrowed string gets
+    // boxed like the `&str` version does.
+    Self::intern(s)
+  }
+}
+
+/// Interns the char as a one-char string.
+///
+/// ```
+/// # use str_id::StrID;
+/// assert_eq!(StrID::from('€'), "€");
+/// assert_eq!(StrID::from('€'), StrID::from("€"));
+/// assert_eq!(StrID::from('🦀'), "🦀");
+/// assert_eq!(StrID::from('🦀'), StrID::from("🦀"));
+/// ```
+impl From<char> for StrID {
+  #[inline]
+  #[track_caller]
+  fn from(c: char) -> Self {
+    // the char is encoded on the stack, so this only allocates if it does
+    // have to be inserted into the cache.
+    let mut buf = [0_u8; 4];
+    Self::intern(&*c.encode_utf8(&mut buf))
+  }
+}
+
+impl From<Arc<str>> for StrID {
+  /// Interns the contents of the `Arc`.
+  ///
+  /// The `Arc` itself is always dropped. Its buffer can't be given to the
+  /// cache, even when the `Arc` is unique, so if the string does have to be
+  /// inserted into the cache it's copied into a fresh allocation.
+  #[inline]
+  #[track_caller]
+  fn from(s: Arc<str>) -> Self {
+    Self::intern(&*s)
+  }
+}
+
+impl From<Rc<str>> for StrID {
+  /// Interns the contents of the `Rc`.
+  ///
+  /// Just like with `Arc<str>`, if the string does have to be inserted into
+  /// the cache it's copied into a fresh allocation.
+  #[inline]
+  #[track_caller]
+  fn from(s: Rc<str>) -> Self {
+    Self::intern(&*s)
+  }
+}
+
+impl From<StrID> for String {
+  /// Copies the interned string into a new `String`.
+  ///
+  /// This is the same as [StrID::to_string_owned].
+  #[inline]
+  fn from(id: StrID) -> Self {
+    id.to_string_owned()
   }
 }
 
@@ -160,9 +2716,395 @@ impl AsRef<str> for StrID {
   }
 }
 
+/// Derefs to the interned string, so all of the `str` methods can be used
+/// directly.
+///
+/// ```
+/// # use str_id::StrID;
+/// let id = StrID::from("hello world");
+/// assert_eq!(id.len(), 11);
+/// assert!(id.contains("o w"));
+/// assert_eq!(&id[..5], "hello");
+///
+/// fn takes_str(s: &str) -> usize {
+///   s.len()
+/// }
+/// assert_eq!(takes_str(&id), 11);
+/// ```
+impl core::ops::Deref for StrID {
+  type Target = str;
+
+  #[inline]
+  fn deref(&self) -> &str {
+    self.as_str()
+  }
+}
+
+impl PartialEq<str> for StrID {
+  #[inline]
+  fn eq(&self, other: &str) -> bool {
+    self.as_str() == other
+  }
+}
+
+impl PartialEq<&str> for StrID {
+  #[inline]
+  fn eq(&self, other: &&str) -> bool {
+    self.as_str() == *other
+  }
+}
+
+impl PartialEq<StrID> for str {
+  #[inline]
+  fn eq(&self, other: &StrID) -> bool {
+    self == other.as_str()
+  }
+}
+
+impl PartialEq<StrID> for &str {
+  #[inline]
+  fn eq(&self, other: &StrID) -> bool {
+    *self == other.as_str()
+  }
+}
+
 impl Default for StrID {
+  /// The ID of the empty string, from [StrID::empty].
   #[inline]
   fn default() -> Self {
-    Self::from(<&str>::default())
+    Self::empty()
+  }
+}
+
+impl core::str::FromStr for StrID {
+  type Err = StrIDExhausted;
+
+  /// Interns the string.
+  ///
+  /// Like the `From` impls, this always inserts (and leaks) the string if it
+  /// isn't already in the cache. The only possible error is running out of ID
+  /// values.
+  #[inline]
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::try_intern(s)
+  }
+}
+
+/// A [StrID] that's ordered by its string rather than by its ID value.
+///
+/// Comparing two of these looks up both strings in the cache and compares them
+/// lexicographically, which is slower than the plain `StrID` order, but gives
+/// alphabetical order (by byte value) when sorting.
+///
+/// Equality and hashing are still by ID value, which agrees with the ordering
+/// since equal strings always have equal IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct ByContent(pub StrID);
+
+impl PartialOrd for ByContent {
+  #[inline]
+  fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for ByContent {
+  #[inline]
+  fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+    // IDs that aren't in the cache all look like the empty string, so the ID
+    // value breaks the tie to keep this consistent with `Eq`.
+    self.0.as_str().cmp(other.0.as_str()).then(self.0.cmp(&other.0))
+  }
+}
+
+/// A [StrID] that hashes and compares as its string, so that it can be
+/// borrowed as a `str`.
+///
+/// This makes it possible to use `&str` values to look up a `HashMap` (or
+/// `HashSet`) that's keyed by interned strings, without interning the
+/// `&str`. The hash is the same as the hash of the string, so it's also the
+/// same in every run of the program (given the same hasher), unlike the hash
+/// of a plain `StrID`. The cost is that hashing and comparing look up the
+/// strings in the cache.
+///
+/// ```
+/// # use str_id::{ContentKey, StrID};
+/// # use std::collections::HashMap;
+/// let mut map = HashMap::new();
+/// map.insert(ContentKey(StrID::from("foo")), 1);
+/// assert_eq!(map.get("foo"), Some(&1));
+/// assert_eq!(map.get("bar"), None);
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct ContentKey(pub StrID);
+
+impl PartialEq for ContentKey {
+  #[inline]
+  fn eq(&self, other: &Self) -> bool {
+    // IDs that aren't in the cache all look like the empty string, so this
+    // has to compare the strings to agree with `Borrow<str>`.
+    self.0 == other.0 || self.0.as_str() == other.0.as_str()
+  }
+}
+impl Eq for ContentKey {}
+
+impl core::hash::Hash for ContentKey {
+  #[inline]
+  fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+    self.0.as_str().hash(state);
+  }
+}
+
+impl core::borrow::Borrow<str> for ContentKey {
+  #[inline]
+  fn borrow(&self) -> &str {
+    self.0.as_str()
+  }
+}
+
+/// A [StrID] that displays as its quoted and escaped string, from
+/// [StrID::quoted].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Quoted(pub StrID);
+
+impl core::fmt::Display for Quoted {
+  #[inline]
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    core::fmt::Debug::fmt(self.0.as_str(), f)
+  }
+}
+
+/// The error for when all of the available [StrID] values have been used.
+///
+/// This is more likely on 32-bit targets, or with the `small-id` feature,
+/// but it's still very unlikely in practice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct StrIDExhausted;
+
+impl core::fmt::Display for StrIDExhausted {
+  #[inline]
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.write_str("exhausted the available StrID values")
+  }
+}
+
+impl core::error::Error for StrIDExhausted {}
+
+#[cfg(test)]
+mod tests {
+  use crate::StrID;
+  use std::sync::{Mutex, MutexGuard, PoisonError};
+
+  /// Locks the global cache for the rest of a test.
+  ///
+  /// Tests run in parallel, and some of them reset the cache, so every test
+  /// that uses the global cache holds this for as long as it runs.
+  pub(crate) fn lock_cache() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  #[test]
+  fn threads_interning_the_same_new_strings_agree() {
+    const STRINGS: usize = 2_000;
+    const THREADS: usize = 8;
+    let _lock = lock_cache();
+    let strs: std::vec::Vec<std::string::String> =
+      (0..STRINGS).map(|i| alloc::format!("stress {i}")).collect();
+    // each thread goes through the strings starting at a different place, so
+    // that threads race to insert many of them.
+    let results: std::vec::Vec<std::vec::Vec<(StrID, bool)>> =
+      std::thread::scope(|scope| {
+        let handles: std::vec::Vec<_> = (0..THREADS)
+          .map(|t| {
+            let strs = &strs;
+            scope.spawn(move || {
+              let mut out = std::vec![None; STRINGS];
+              for i in 0..STRINGS {
+                let i = (i + t * STRINGS / THREADS) % STRINGS;
+                out[i] = Some(StrID::intern_reporting(&strs[i]));
+              }
+              out.into_iter().map(Option::unwrap).collect()
+            })
+          })
+          .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+      });
+    let mut ids: std::vec::Vec<StrID> = std::vec::Vec::new();
+    for (i, s) in strs.iter().enumerate() {
+      let id = results[0][i].0;
+      assert_eq!(id, s.as_str());
+      assert!(results.iter().all(|r| r[i].0 == id));
+      let inserted = results.iter().filter(|r| r[i].1).count();
+      assert_eq!(inserted, 1, "{s:?} was inserted {inserted} times");
+      ids.push(id);
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids.len(), STRINGS);
+  }
+
+  #[cfg(all(feature = "small-id", debug_assertions))]
+  #[test]
+  #[should_panic = "too big"]
+  fn small_id_from_usize_unchecked_rejects_big_values() {
+    let n = core::num::NonZeroUsize::new(u32::MAX as usize + 1).unwrap();
+    let _ = StrID::from_usize_unchecked(n);
+  }
+
+  #[test]
+  fn preload_interns_each_chunk_before_reading_the_next() {
+    use crate::{PRELOAD_CHUNK, preload};
+    let _lock = lock_cache();
+    let name = |i: usize| alloc::format!("preload chunk {i}");
+    let mut seen_early = false;
+    preload((0..PRELOAD_CHUNK * 2).map(|i| {
+      if i == PRELOAD_CHUNK + 1 {
+        seen_early = StrID::get(&name(0)).is_some();
+      }
+      name(i)
+    }));
+    assert!(seen_early);
+    assert!(StrID::get(&name(PRELOAD_CHUNK * 2 - 1)).is_some());
+  }
+
+  #[test]
+  fn equal_statics_at_different_addresses_share_the_first() {
+    use alloc::{boxed::Box, string::String};
+    let _lock = lock_cache();
+    let leaked: &'static str =
+      Box::leak(String::from("from_static dedup").into_boxed_str());
+    let literal: &'static str = "from_static dedup";
+    assert_ne!(leaked.as_ptr(), literal.as_ptr());
+    let first = StrID::from_static(leaked);
+    let second = StrID::from_static(literal);
+    assert_eq!(first, second);
+    assert_eq!(second.as_str().as_ptr(), leaked.as_ptr());
+  }
+
+  #[cfg(feature = "testing")]
+  #[test]
+  fn cow_variants_count_hits_and_misses() {
+    use crate::{intern_stats, reset_cache};
+    use alloc::{borrow::Cow, string::String};
+    let _lock = lock_cache();
+    reset_cache();
+    let borrowed = StrID::from(Cow::Borrowed("a"));
+    let owned = StrID::from(Cow::<str>::Owned(String::from("b")));
+    assert_eq!((intern_stats().hits, intern_stats().misses), (0, 2));
+    // each one hits the other's entry.
+    assert_eq!(StrID::from(Cow::<str>::Owned(String::from("a"))), borrowed);
+    assert_eq!(StrID::from(Cow::Borrowed("b")), owned);
+    assert_eq!((intern_stats().hits, intern_stats().misses), (2, 2));
+    assert_eq!(intern_stats().entries, 2);
+  }
+
+  #[cfg(feature = "testing")]
+  #[test]
+  fn intern_concat_counts_one_miss_then_hits() {
+    use crate::{intern_concat, intern_stats, interned_count, reset_cache};
+    let _lock = lock_cache();
+    reset_cache();
+    let id = intern_concat(&["module", "::", "name"]);
+    assert_eq!((intern_stats().hits, intern_stats().misses), (0, 1));
+    assert_eq!(intern_concat(&["module::", "name"]), id);
+    assert_eq!((intern_stats().hits, intern_stats().misses), (1, 1));
+    // the parts aren't interned on their own.
+    assert_eq!(interned_count(), 1);
+  }
+
+  #[cfg(feature = "testing")]
+  #[test]
+  fn from_static_and_concat_intern_one_entry() {
+    use crate::{interned_count, reset_cache};
+    use alloc::string::String;
+    let _lock = lock_cache();
+    reset_cache();
+    let a = StrID::from(String::from("x"));
+    assert_eq!(StrID::from_static("x"), a);
+    assert_eq!(interned_count(), 1);
+
+    reset_cache();
+    let id = StrID::concat("module", "::name");
+    assert_eq!(StrID::concat("module::", "name"), id);
+    // neither part is interned on its own.
+    assert_eq!(interned_count(), 1);
+  }
+
+  #[cfg(feature = "tracing")]
+  mod tracing_events {
+    use super::lock_cache;
+    use crate::*;
+    use std::{
+      sync::Mutex,
+      thread::{self, ThreadId},
+    };
+    use tracing::{
+      Event, Metadata, Subscriber,
+      field::{Field, Visit},
+      span,
+    };
+
+    /// The `count` of every size event, with the thread that emitted it.
+    static THRESHOLDS: Mutex<Vec<(ThreadId, u64)>> = Mutex::new(Vec::new());
+
+    struct ThresholdRecorder;
+    impl Subscriber for ThresholdRecorder {
+      fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+      }
+      fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+      }
+      fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+      fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+      fn event(&self, event: &Event<'_>) {
+        struct Count(Option<u64>);
+        impl Visit for Count {
+          fn record_u64(&mut self, field: &Field, value: u64) {
+            if field.name() == "count" {
+              self.0 = Some(value);
+            }
+          }
+          fn record_debug(&mut self, _: &Field, _: &dyn core::fmt::Debug) {}
+        }
+        if *event.metadata().level() != tracing::Level::DEBUG {
+          return;
+        }
+        let mut count = Count(None);
+        event.record(&mut count);
+        if let Some(count) = count.0 {
+          THRESHOLDS.lock().unwrap().push((thread::current().id(), count));
+        }
+      }
+      fn enter(&self, _: &span::Id) {}
+      fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn tracing_reports_each_crossed_threshold() {
+      let _lock = lock_cache();
+      let _ = tracing::subscriber::set_global_default(ThresholdRecorder);
+      set_tracing_interval(4);
+      let strs: Vec<String> =
+        (0..12).map(|i| alloc::format!("tracing threshold {i}")).collect();
+      intern_all(&strs);
+      // the batch takes the count through 12 values in one step, which crosses
+      // exactly 3 multiples of 4, no matter where it started.
+      let me = thread::current().id();
+      let counts: Vec<u64> = THRESHOLDS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(thread, _)| *thread == me)
+        .map(|(_, count)| *count)
+        .collect();
+      assert_eq!(counts.len(), 3);
+      assert!(counts.iter().all(|count| count % 4 == 0));
+      assert!(counts.windows(2).all(|pair| pair[1] == pair[0] + 4));
+    }
   }
 }
@@ -0,0 +1,84 @@
+//! Separate sets of strings that share the global cache.
+
+use crate::{StaticStr, StrID, intern_concat};
+use alloc::string::String;
+
+/// A prefix that keeps one set of interned strings apart from another.
+///
+/// Interning `"name"` in a `"types"` namespace actually interns
+/// `"types\0name"`, with a NUL character between the namespace's name and the
+/// string. So the same string interned in two different namespaces (or in a
+/// namespace and directly) gets two different IDs. Everything still goes in
+/// the global cache, and [StrID::as_str] on such an ID gives the whole
+/// prefixed string. Use [as_str](Self::as_str) on the namespace to get the
+/// string without the prefix.
+///
+/// The namespace's name shouldn't contain a NUL character itself, or strings
+/// from different namespaces could end up the same. For example `"a\0b"` in
+/// namespace `"x"` is the same as `"b"` in namespace `"x\0a"`.
+///
+/// ```
+/// # use str_id::Namespace;
+/// let types = Namespace::new("types");
+/// let fields = Namespace::new("fields");
+/// let a = types.intern("name");
+/// let b = fields.intern("name");
+/// assert_ne!(a, b);
+/// assert_eq!(types.as_str(a), Some("name"));
+/// assert_eq!(fields.as_str(a), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Namespace {
+  prefix: StaticStr,
+}
+impl Namespace {
+  /// Makes a namespace with the given name.
+  ///
+  /// The prefix (the name and the separator) is itself interned, so making
+  /// the same namespace again doesn't use any more memory.
+  #[inline]
+  #[track_caller]
+  pub fn new(name: &str) -> Self {
+    Self { prefix: intern_concat(&[name, "\0"]).as_str() }
+  }
+
+  /// Gets the name of this namespace.
+  #[inline]
+  #[must_use]
+  pub fn name(&self) -> StaticStr {
+    &self.prefix[..self.prefix.len() - 1]
+  }
+
+  /// Interns a string in this namespace.
+  #[inline]
+  #[track_caller]
+  pub fn intern(&self, s: &str) -> StrID {
+    intern_concat(&[self.prefix, s])
+  }
+
+  /// Gets the ID of a string in this namespace, if it's already interned.
+  #[inline]
+  #[must_use]
+  pub fn get(&self, s: &str) -> Option<StrID> {
+    let mut key = String::with_capacity(self.prefix.len() + s.len());
+    key.push_str(self.prefix);
+    key.push_str(s);
+    StrID::get(&key)
+  }
+
+  /// Gets the string of an ID from this namespace, without the prefix.
+  ///
+  /// This gives `None` if the ID isn't from this namespace.
+  #[inline]
+  #[must_use]
+  pub fn as_str(&self, id: StrID) -> Option<StaticStr> {
+    id.as_str().strip_prefix(self.prefix)
+  }
+
+  /// Checks if an ID is from this namespace.
+  #[inline]
+  #[must_use]
+  pub fn contains(&self, id: StrID) -> bool {
+    self.as_str(id).is_some()
+  }
+}
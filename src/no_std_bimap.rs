@@ -0,0 +1,73 @@
+//! A bimap for when `std` isn't available.
+//!
+//! `bimap` only has a hash based bimap with `std`, so this is two `hashbrown`
+//! maps that are kept in sync. Since both sides of the cache are `Copy`, it
+//! doesn't need to share the values between the maps like `bimap` does.
+
+use crate::{StaticStr, StrID, hasher::CacheBuildHasher};
+use hashbrown::HashMap;
+
+pub(crate) struct BiMap {
+  left: HashMap<StrID, StaticStr, CacheBuildHasher>,
+  right: HashMap<StaticStr, StrID, CacheBuildHasher>,
+}
+impl BiMap {
+  #[inline]
+  pub(crate) fn with_hashers(
+    left: CacheBuildHasher, right: CacheBuildHasher,
+  ) -> Self {
+    Self {
+      left: HashMap::with_hasher(left),
+      right: HashMap::with_hasher(right),
+    }
+  }
+
+  #[inline]
+  pub(crate) fn get_by_left(&self, id: &StrID) -> Option<&StaticStr> {
+    self.left.get(id)
+  }
+
+  #[inline]
+  pub(crate) fn get_by_right(&self, s: &str) -> Option<&StrID> {
+    self.right.get(s)
+  }
+
+  #[inline]
+  pub(crate) fn contains_left(&self, id: &StrID) -> bool {
+    self.left.contains_key(id)
+  }
+
+  /// Inserts a pair, which must not already be in either side of the map.
+  #[inline]
+  pub(crate) fn insert(&mut self, id: StrID, s: StaticStr) {
+    self.left.insert(id, s);
+    self.right.insert(s, id);
+  }
+
+  #[inline]
+  pub(crate) fn len(&self) -> usize {
+    self.left.len()
+  }
+
+  #[inline]
+  pub(crate) fn right_values(&self) -> impl Iterator<Item = &StaticStr> {
+    self.left.values()
+  }
+
+  #[inline]
+  pub(crate) fn iter(&self) -> impl Iterator<Item = (&StrID, &StaticStr)> {
+    self.left.iter()
+  }
+
+  #[inline]
+  pub(crate) fn reserve(&mut self, additional: usize) {
+    self.left.reserve(additional);
+    self.right.reserve(additional);
+  }
+
+  #[inline]
+  pub(crate) fn shrink_to_fit(&mut self) {
+    self.left.shrink_to_fit();
+    self.right.shrink_to_fit();
+  }
+}
@@ -0,0 +1,108 @@
+//! A lock free table for looking up the string of an ID, used with the
+//! `arc-swap` feature.
+//!
+//! Each ID has a slot in the table, found from the counter value that the ID
+//! was made from (the ID value divided by the shard count). The table is a
+//! list of chunks, each twice the size of the one before, so growing the table
+//! only has to copy the (short) list of chunks, never the slots themselves.
+//! The list is swapped out with an `ArcSwap`, so a read is just an atomic load
+//! and some indexing. Growing it is serialized by a mutex.
+//!
+//! A slot also stores the ID that it's for, and when anything is off (a slot
+//! that's unset, or that holds some other ID) the caller falls back to looking
+//! in the cache, so the table only ever has to be right when it says it found
+//! something.
+//!
+//! When an ID lands past the end of the table (which only happens when the
+//! counter jumps ahead, such as with `set_next_id` or an `import_snapshot`
+//! whose IDs start high), the missing chunks are filled in, as long as that
+//! keeps the table within the first [GAP_CHUNKS] chunks. Past that, the table
+//! only ever grows by one chunk at a time, so that memory use stays in
+//! proportion to the number of strings. **An ID that lands further ahead is
+//! left out of the table, and so is every ID after it**, since they're all in
+//! even later chunks. From then on, looking up the string of a new ID always
+//! falls back to the cache's lock.
+
+use crate::{StaticStr, StrID, shard_count};
+use arc_swap::ArcSwapOption;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+type Slot = OnceLock<(StrID, StaticStr)>;
+type Chunks = Vec<Arc<[Slot]>>;
+
+/// The number of slots in the first chunk, which is also the number of
+/// counter values that the chunk index is offset by. Must be a power of two.
+const FIRST_CHUNK: usize = 64;
+
+/// How many chunks the table can have before it stops filling in missing
+/// chunks. These chunks hold about a million slots, which is about 32MiB.
+const GAP_CHUNKS: usize = 14;
+
+static TABLE: ArcSwapOption<Chunks> = ArcSwapOption::const_empty();
+static GROW: Mutex<()> = Mutex::new(());
+
+/// Gets the chunk index and the slot index within the chunk for an ID.
+#[inline]
+fn locate(id: StrID) -> Option<(usize, usize)> {
+  let n = (id.as_usize() / shard_count()).checked_add(FIRST_CHUNK)?;
+  let chunk = n.ilog2() - FIRST_CHUNK.ilog2();
+  Some((chunk as usize, n - (FIRST_CHUNK << chunk)))
+}
+
+/// Gets the string of an ID, if it's in the table.
+#[inline]
+pub(crate) fn get(id: StrID) -> Option<StaticStr> {
+  let (chunk, slot) = locate(id)?;
+  let chunks = TABLE.load();
+  let (slot_id, s) = chunks.as_ref()?.get(chunk)?[slot].get()?;
+  if *slot_id == id { Some(*s) } else { None }
+}
+
+/// Puts an ID and its string into the table.
+///
+/// This is called with the write lock of the ID's shard held, so that the
+/// entry is in the table before the ID can be given to anyone.
+#[inline]
+pub(crate) fn insert(id: StrID, s: StaticStr) {
+  let Some((chunk, slot)) = locate(id) else { return };
+  let chunks = match TABLE.load_full() {
+    Some(chunks) if chunks.len() > chunk => chunks,
+    _ => match grow(chunk) {
+      Some(chunks) => chunks,
+      None => return,
+    },
+  };
+  // if the slot is somehow already taken, lookups of this ID just fall back
+  // to the cache.
+  let _ = chunks[chunk][slot].set((id, s));
+}
+
+/// Grows the table to have the given chunk, if that's the next chunk or it's
+/// one of the first [GAP_CHUNKS] chunks.
+#[cold]
+fn grow(chunk: usize) -> Option<Arc<Chunks>> {
+  let _guard = GROW.lock().unwrap_or_else(PoisonError::into_inner);
+  // some other thread might have grown the table while we waited.
+  let mut chunks: Chunks = match TABLE.load_full() {
+    Some(chunks) if chunks.len() > chunk => return Some(chunks),
+    Some(chunks) => Chunks::clone(&chunks),
+    None => Chunks::new(),
+  };
+  if chunks.len() < chunk && chunk >= GAP_CHUNKS {
+    return None;
+  }
+  while chunks.len() <= chunk {
+    let len = FIRST_CHUNK << chunks.len();
+    chunks.push((0..len).map(|_| Slot::new()).collect());
+  }
+  let chunks = Arc::new(chunks);
+  TABLE.store(Some(Arc::clone(&chunks)));
+  Some(chunks)
+}
+
+/// Empties the table.
+#[cfg(feature = "testing")]
+pub(crate) fn clear() {
+  let _guard = GROW.lock().unwrap_or_else(PoisonError::into_inner);
+  TABLE.store(None);
+}
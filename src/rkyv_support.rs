@@ -0,0 +1,62 @@
+//! rkyv support, archiving a [StrID] as its string.
+//!
+//! The raw ID value isn't stable across program runs, so it's never archived.
+//! The archived form is an `ArchivedString`, which can be read as a `&str`
+//! straight out of the buffer without touching the cache. The string is only
+//! interned in the current process when the archive is deserialized.
+
+use crate::StrID;
+use rkyv::{
+  Archive, Deserialize, Place, Serialize,
+  rancor::{Fallible, Source},
+  string::{ArchivedString, StringResolver},
+};
+
+impl Archive for StrID {
+  type Archived = ArchivedString;
+  type Resolver = StringResolver;
+
+  #[inline]
+  fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+    ArchivedString::resolve_from_str(self.as_str(), resolver, out);
+  }
+}
+
+impl<S> Serialize<S> for StrID
+where
+  S: Fallible + rkyv::ser::Writer + ?Sized,
+  S::Error: Source,
+{
+  #[inline]
+  fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+    ArchivedString::serialize_from_str(self.as_str(), serializer)
+  }
+}
+
+impl<D: Fallible + ?Sized> Deserialize<StrID, D> for ArchivedString {
+  #[inline]
+  fn deserialize(&self, _: &mut D) -> Result<StrID, D::Error> {
+    Ok(StrID::from(self.as_str()))
+  }
+}
+
+#[cfg(all(test, feature = "rkyv"))]
+mod tests {
+  use crate::{StrID, tests::lock_cache};
+  use rkyv::{rancor::Error, string::ArchivedString, util::AlignedVec};
+
+  #[test]
+  fn round_trips_through_an_aligned_vec() {
+    let _lock = lock_cache();
+    for s in ["rkyv round trip", ""] {
+      let id = StrID::from(s);
+      let bytes: AlignedVec = rkyv::to_bytes::<Error>(&id).unwrap();
+      let archived = rkyv::access::<ArchivedString, Error>(&bytes).unwrap();
+      // the archived string is read straight out of the buffer.
+      assert_eq!(archived.as_str(), s);
+      assert!(bytes.as_ptr_range().contains(&archived.as_str().as_ptr()));
+      let back: StrID = rkyv::deserialize::<StrID, Error>(archived).unwrap();
+      assert_eq!(back, id);
+    }
+  }
+}
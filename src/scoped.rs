@@ -0,0 +1,211 @@
+//! An interner whose IDs can't outlive it.
+
+use core::{fmt, marker::PhantomData};
+use std::{
+  collections::HashMap,
+  sync::{Arc, OnceLock, PoisonError, RwLock},
+};
+
+/// The number of slots in the first chunk. Each chunk after that is twice the
+/// size of the one before. Must be a power of two.
+const FIRST_CHUNK: usize = 64;
+
+/// Enough chunks to give every possible `usize` index a slot.
+const CHUNK_COUNT: usize = (usize::BITS - FIRST_CHUNK.ilog2()) as usize;
+
+type Chunk = Box<[OnceLock<Arc<str>>]>;
+
+/// An ID value from a [ScopedInterner].
+///
+/// The ID borrows the interner that created it, so the borrow checker makes
+/// sure that it's not used after the interner (and its strings) are gone. It
+/// can still be passed to some other `ScopedInterner` that lives at least as
+/// long, which gives some unrelated string, or nothing at all.
+pub struct ScopedStrID<'scope> {
+  index: usize,
+  scope: PhantomData<&'scope ScopedInterner>,
+}
+impl ScopedStrID<'_> {
+  /// Unwraps the value into a raw `usize`.
+  ///
+  /// The IDs of a `ScopedInterner` count up from 0.
+  #[inline]
+  #[must_use]
+  pub const fn as_usize(self) -> usize {
+    self.index
+  }
+}
+impl Clone for ScopedStrID<'_> {
+  #[inline]
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+impl Copy for ScopedStrID<'_> {}
+impl PartialEq for ScopedStrID<'_> {
+  #[inline]
+  fn eq(&self, other: &Self) -> bool {
+    self.index == other.index
+  }
+}
+impl Eq for ScopedStrID<'_> {}
+impl PartialOrd for ScopedStrID<'_> {
+  #[inline]
+  fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+    Some(self.cmp(other))
+  }
+}
+impl Ord for ScopedStrID<'_> {
+  #[inline]
+  fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+    self.index.cmp(&other.index)
+  }
+}
+impl core::hash::Hash for ScopedStrID<'_> {
+  #[inline]
+  fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+    self.index.hash(state);
+  }
+}
+impl fmt::Debug for ScopedStrID<'_> {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("ScopedStrID").field(&self.index).finish()
+  }
+}
+
+/// Interns str slices for as long as the interner lives, giving back IDs that
+/// borrow the interner.
+///
+/// This is the middle ground between the global cache, which keeps every
+/// string forever, and an [Interner](crate::Interner), whose IDs are plain
+/// values that can outlive it. All of the strings are freed when the
+/// `ScopedInterner` is dropped, and since each [ScopedStrID] borrows the
+/// interner, the IDs can't be kept around past that point.
+///
+/// Interning only needs `&self`, so a `ScopedInterner` can be shared between
+/// threads. Just like the global cache, it takes a read lock to look for the
+/// string, and only takes the write lock (and checks again) when the string
+/// has to be inserted. Getting the string of an ID doesn't lock at all.
+///
+/// ```
+/// # use str_id::ScopedInterner;
+/// let interner = ScopedInterner::new();
+/// let a = interner.intern("foo");
+/// assert_eq!(interner.intern("foo"), a);
+/// assert_eq!(interner.resolve(a), Some("foo"));
+/// ```
+///
+/// The IDs can't be used once the interner is gone:
+///
+/// ```compile_fail
+/// # use str_id::ScopedInterner;
+/// let interner = ScopedInterner::new();
+/// let a = interner.intern("foo");
+/// drop(interner);
+/// println!("{a:?}");
+/// ```
+pub struct ScopedInterner {
+  map: RwLock<HashMap<Arc<str>, usize>>,
+  chunks: [OnceLock<Chunk>; CHUNK_COUNT],
+}
+impl ScopedInterner {
+  /// Makes a new, empty interner.
+  #[inline]
+  #[must_use]
+  pub fn new() -> Self {
+    Self {
+      map: RwLock::new(HashMap::new()),
+      chunks: core::array::from_fn(|_| OnceLock::new()),
+    }
+  }
+
+  /// Gets the ID of a str slice, inserting it into this interner if
+  /// necessary.
+  #[inline]
+  pub fn intern(&self, s: &str) -> ScopedStrID<'_> {
+    if let Some(id) = self.get(s) {
+      return id;
+    }
+    // the string is copied before taking the write lock, just like with the
+    // global cache.
+    let s: Arc<str> = Arc::from(s);
+    let mut map = self.map.write().unwrap_or_else(PoisonError::into_inner);
+    // It's *possible* that the string was inserted after we dropped the
+    // reader before we acquired the writer, so we must check again.
+    if let Some(index) = map.get(&s) {
+      return self.id(*index);
+    }
+    let index = map.len();
+    let (chunk, slot) = locate(index);
+    let chunk = self.chunks[chunk].get_or_init(|| {
+      (0..FIRST_CHUNK << chunk).map(|_| OnceLock::new()).collect()
+    });
+    // the slot is set before the ID exists, so it's always set when resolved.
+    let _ = chunk[slot].set(Arc::clone(&s));
+    map.insert(s, index);
+    self.id(index)
+  }
+
+  /// Gets the ID of a str slice, if it's already in this interner.
+  #[inline]
+  #[must_use]
+  pub fn get(&self, s: &str) -> Option<ScopedStrID<'_>> {
+    let map = self.map.read().unwrap_or_else(PoisonError::into_inner);
+    map.get(s).map(|index| self.id(*index))
+  }
+
+  /// Gets the str slice associated with an ID, if the ID is in this
+  /// interner.
+  ///
+  /// The string borrows the interner, and this never locks.
+  #[inline]
+  #[must_use]
+  pub fn resolve(&self, id: ScopedStrID<'_>) -> Option<&str> {
+    let (chunk, slot) = locate(id.index);
+    self.chunks[chunk].get()?.get(slot)?.get().map(|s| &**s)
+  }
+
+  /// Gets the number of strings in this interner.
+  #[inline]
+  #[must_use]
+  pub fn len(&self) -> usize {
+    self.map.read().unwrap_or_else(PoisonError::into_inner).len()
+  }
+
+  /// Checks if this interner has no strings in it.
+  #[inline]
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  #[inline]
+  fn id(&self, index: usize) -> ScopedStrID<'_> {
+    ScopedStrID { index, scope: PhantomData }
+  }
+}
+
+impl Default for ScopedInterner {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl fmt::Debug for ScopedInterner {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ScopedInterner").field("len", &self.len()).finish()
+  }
+}
+
+/// Gets the chunk index and the slot index within the chunk for an index.
+#[inline]
+fn locate(index: usize) -> (usize, usize) {
+  // this can't overflow, because the map would run out of memory long before
+  // it had `usize::MAX - FIRST_CHUNK` entries.
+  let n = index + FIRST_CHUNK;
+  let chunk = (n.ilog2() - FIRST_CHUNK.ilog2()) as usize;
+  (chunk, n - (FIRST_CHUNK << chunk))
+}
@@ -0,0 +1,154 @@
+//! Serde support, serializing a [StrID] as its string.
+//!
+//! The raw ID value isn't stable across program runs, so it's never what gets
+//! serialized. Deserializing interns the string in the current process, which
+//! gives back an ID that's valid here.
+//!
+//! For data that's only ever read back by the same running process,
+//! [StrIDRaw] serializes the raw ID value instead.
+
+use crate::{CacheReport, StrID};
+use alloc::string::String;
+use core::fmt;
+use serde::{
+  Deserialize, Deserializer, Serialize, Serializer,
+  de::{Error, Unexpected, Visitor},
+  ser::SerializeStruct,
+};
+
+impl Serialize for StrID {
+  #[inline]
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(self.as_str())
+  }
+}
+
+struct StrIDVisitor;
+
+impl Visitor<'_> for StrIDVisitor {
+  type Value = StrID;
+
+  #[inline]
+  fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("a string")
+  }
+
+  #[inline]
+  fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+    Ok(StrID::from(v))
+  }
+
+  #[inline]
+  fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+    Ok(StrID::from(v))
+  }
+}
+
+impl<'de> Deserialize<'de> for StrID {
+  #[inline]
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    deserializer.deserialize_str(StrIDVisitor)
+  }
+}
+
+/// A wrapper that serializes a [StrID] as its raw `usize` value.
+///
+/// **The raw value is only meaningful within the process that created it.**
+/// This is for formats that are written and read back by the same program
+/// run, such as an in-memory snapshot. If the data might be read by some
+/// other run of the program, serialize the [StrID] itself, which uses the
+/// string.
+///
+/// Deserializing a value that isn't a live ID in the cache is an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct StrIDRaw(pub StrID);
+
+impl Serialize for StrIDRaw {
+  #[inline]
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.0.as_usize().serialize(serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for StrIDRaw {
+  #[inline]
+  fn deserialize<D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<Self, D::Error> {
+    let n = usize::deserialize(deserializer)?;
+    match StrID::try_from_usize(n) {
+      Some(id) => Ok(Self(id)),
+      None => Err(D::Error::invalid_value(
+        Unexpected::Unsigned(n as u64),
+        &"a live StrID value",
+      )),
+    }
+  }
+}
+
+impl Serialize for CacheReport {
+  #[inline]
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut s = serializer.serialize_struct("CacheReport", 5)?;
+    s.serialize_field("entries", &self.entries)?;
+    s.serialize_field("total_bytes", &self.total_bytes)?;
+    s.serialize_field("longest_len", &self.longest_len)?;
+    s.serialize_field("shortest_len", &self.shortest_len)?;
+    s.serialize_field("average_len", &self.average_len)?;
+    s.end()
+  }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+  use super::*;
+  use crate::tests::lock_cache;
+  use serde::de::{IntoDeserializer, value};
+
+  /// Deserializes with `visit_str`.
+  fn from_str(s: &str) -> Result<StrID, value::Error> {
+    StrID::deserialize(value::StrDeserializer::new(s))
+  }
+
+  /// Deserializes with `visit_string`.
+  fn from_string(s: &str) -> Result<StrID, value::Error> {
+    StrID::deserialize(value::StringDeserializer::new(String::from(s)))
+  }
+
+  #[test]
+  fn deserializes_strings() {
+    let _lock = lock_cache();
+    for s in ["serde string", ""] {
+      let id = StrID::from(s);
+      assert_eq!(from_str(id.as_str()), Ok(id));
+      assert_eq!(from_string(id.as_str()), Ok(id));
+    }
+    // a string that wasn't interned yet is interned.
+    let id = from_string("serde string not interned yet").unwrap();
+    assert_eq!(id, "serde string not interned yet");
+    assert_eq!(from_str("serde string not interned yet"), Ok(id));
+  }
+
+  fn raw_from(n: usize) -> Result<StrIDRaw, value::Error> {
+    StrIDRaw::deserialize(n.into_deserializer())
+  }
+
+  #[test]
+  fn raw_deserializes_live_ids() {
+    let _lock = lock_cache();
+    let id = StrID::from("serde raw live");
+    assert_eq!(raw_from(id.as_usize()), Ok(StrIDRaw(id)));
+  }
+
+  #[test]
+  fn raw_rejects_ids_that_are_not_live() {
+    let _lock = lock_cache();
+    assert!(raw_from(0).is_err());
+    assert!(raw_from(usize::MAX).is_err());
+    // far past anything that the tests could have allocated.
+    assert!(raw_from(StrID::MAX.as_usize() / 2).is_err());
+  }
+}
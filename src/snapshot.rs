@@ -0,0 +1,503 @@
+//! Saving the whole cache as bytes, and restoring it later.
+
+use crate::{
+  IdInt, IdValue, NEXT_STR_ID, StaticStr, StrID, cache, intern_batch, read_all,
+  shard_of_id, shard_of_str, write_all,
+};
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+use core::{fmt, sync::atomic::Ordering};
+
+/// The magic bytes at the start of a snapshot.
+const MAGIC: [u8; 4] = *b"SIDS";
+
+/// The version of the snapshot format that this crate writes.
+const VERSION: u8 = 2;
+
+/// The size of the checksum at the end of a snapshot.
+const CHECKSUM_LEN: usize = 4;
+
+/// Writes every entry in the cache into a byte buffer.
+///
+/// The buffer can be given to [import_snapshot] in some other run of the
+/// program to get all of the same ID values back.
+///
+/// Every shard of the cache is read locked while the entries are gathered, so
+/// the snapshot is a consistent view of the cache.
+///
+/// ## Format
+///
+/// The layout is simple enough to read from outside of Rust. All integers are
+/// little-endian.
+///
+/// * The magic bytes `b"SIDS"`.
+/// * The format version, as a `u8`. This is currently 2.
+/// * The number of entries, as a `u64`.
+/// * Each entry, in ascending ID order:
+///   * The ID value, as a `u64`.
+///   * The length of the string in bytes, as a `u64`.
+///   * The string's bytes, which are UTF-8.
+/// * The CRC-32 (the same one as zlib and PNG use) of all of the bytes before
+///   it, as a `u32`.
+#[must_use]
+pub fn export_snapshot() -> Vec<u8> {
+  let mut entries: Vec<(StrID, StaticStr)> = Vec::new();
+  for read in read_all() {
+    entries.extend(read.iter().map(|(id, s)| (*id, *s)));
+  }
+  entries.sort_unstable_by_key(|(id, _)| *id);
+  let string_bytes: usize = entries.iter().map(|(_, s)| s.len()).sum();
+  let mut out = Vec::with_capacity(
+    MAGIC.len() + 1 + 8 + entries.len() * 16 + string_bytes + CHECKSUM_LEN,
+  );
+  out.extend_from_slice(&MAGIC);
+  out.push(VERSION);
+  out.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+  for (id, s) in entries {
+    out.extend_from_slice(&(id.as_usize() as u64).to_le_bytes());
+    out.extend_from_slice(&(s.len() as u64).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+  }
+  let checksum = crc32(&out);
+  out.extend_from_slice(&checksum.to_le_bytes());
+  out
+}
+
+/// Inserts every entry of a snapshot from [export_snapshot] into the cache,
+/// keeping the ID values that they had when they were exported.
+///
+/// The whole snapshot is checked before anything is inserted, so on an error
+/// the cache is left as it was. That includes the checksum, so a snapshot
+/// that was damaged after it was written is an error instead of a source of
+/// garbage strings. A snapshot that was cut short is [Truncated] rather than
+/// [BadChecksum], since the lengths in it are checked before the checksum. An entry conflicts with the cache when its ID
+/// is already used for some other string, or when its string is already
+/// interned with some other ID. Entries that are already in the cache exactly
+/// as they are in the snapshot are fine, and are skipped.
+///
+/// Afterwards, newly interned strings will always get IDs past those in the
+/// snapshot.
+///
+/// When the cache is sharded, the snapshot must have been exported with the
+/// same [shard_count](crate::shard_count), or the IDs will be in the wrong
+/// shards for their strings.
+///
+/// [Truncated]: SnapshotError::Truncated
+/// [BadChecksum]: SnapshotError::BadChecksum
+pub fn import_snapshot(bytes: &[u8]) -> Result<(), SnapshotError> {
+  let mut reader = Reader { bytes };
+  if reader.take(MAGIC.len())? != MAGIC {
+    return Err(SnapshotError::BadMagic);
+  }
+  let version = reader.u8()?;
+  if version != VERSION {
+    return Err(SnapshotError::UnsupportedVersion(version));
+  }
+  // the layout is read first, so that a snapshot that was cut short is
+  // reported as that instead of as a bad checksum.
+  let count = reader.u64()?;
+  let mut raw_entries: Vec<(u64, &[u8])> = Vec::new();
+  for _ in 0..count {
+    let raw_id = reader.u64()?;
+    let len =
+      usize::try_from(reader.u64()?).map_err(|_| SnapshotError::Truncated)?;
+    raw_entries.push((raw_id, reader.take(len)?));
+  }
+  let checksum = reader.take(CHECKSUM_LEN)?;
+  if !reader.bytes.is_empty() {
+    return Err(SnapshotError::TrailingBytes);
+  }
+  let covered = bytes.len() - CHECKSUM_LEN;
+  if crc32(&bytes[..covered]).to_le_bytes() != checksum {
+    return Err(SnapshotError::BadChecksum);
+  }
+  let mut entries: Vec<(StrID, &str)> = Vec::with_capacity(raw_entries.len());
+  for (raw_id, s) in raw_entries {
+    let id = IdInt::try_from(raw_id)
+      .ok()
+      .and_then(IdValue::new)
+      .map(StrID)
+      .ok_or(SnapshotError::InvalidId(raw_id))?;
+    let s = core::str::from_utf8(s).map_err(|_| SnapshotError::InvalidUtf8)?;
+    let in_order = entries.last().is_none_or(|(prev, _)| *prev < id);
+    if !in_order || shard_of_id(id) != shard_of_str(s) {
+      return Err(SnapshotError::InvalidId(raw_id));
+    }
+    entries.push((id, s));
+  }
+
+  // The IDs are in order, so they're all distinct, but the strings also need
+  // to be distinct.
+  let mut strs: Vec<(&str, StrID)> =
+    entries.iter().map(|(id, s)| (*s, *id)).collect();
+  strs.sort_unstable();
+  if let Some(pair) = strs.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+    return Err(SnapshotError::IdConflict(pair[1].1.as_usize() as u64));
+  }
+
+  let mut shards = write_all();
+  for (id, s) in entries.iter() {
+    let shard = &shards[shard_of_id(*id)];
+    let ok = match (shard.get_by_left(id), shard.get_by_right(s)) {
+      (None, None) => true,
+      (Some(cached_s), Some(cached_id)) => cached_s == s && cached_id == id,
+      _ => false,
+    };
+    if !ok {
+      return Err(SnapshotError::IdConflict(id.as_usize() as u64));
+    }
+  }
+  let shard_count = cache().len() as IdInt;
+  let mut next = 0;
+  #[cfg(feature = "tracing")]
+  let mut inserted = 0;
+  for (id, s) in entries {
+    let shard = &mut shards[shard_of_id(id)];
+    if !shard.contains_left(&id) {
+      let leaked: StaticStr = Box::leak(Box::from(s));
+      shard.insert(id, leaked);
+      #[cfg(feature = "arc-swap")]
+      crate::reverse_table::insert(id, leaked);
+      #[cfg(feature = "tracing")]
+      {
+        inserted += 1;
+      }
+    }
+    next = next.max(id.0.get() / shard_count);
+  }
+  match next.checked_add(1) {
+    // if the ID values had already run out, they have to stay that way.
+    Some(next) => {
+      let _ =
+        NEXT_STR_ID.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+          if n == 0 { None } else { Some(n.max(next)) }
+        });
+    }
+    // the snapshot used the very last ID value, so there are none left.
+    None => NEXT_STR_ID.store(0, Ordering::Relaxed),
+  }
+  drop(shards);
+  #[cfg(feature = "tracing")]
+  crate::trace_grown(inserted);
+  Ok(())
+}
+
+/// Gets the string of each distinct ID in the slice, as a table that can be
+/// saved along with the raw ID values.
+///
+/// This is a smaller alternative to [export_snapshot] for saving data that's
+/// full of IDs: write the raw [as_usize](StrID::as_usize) values as they are,
+/// and write this table once. **Only the IDs in the slice are exported**, not
+/// the rest of the cache. The table is sorted by ID value, with each ID in it
+/// just once no matter how many times it's in the slice.
+///
+/// Use [import_table] in some other run of the program to turn the old ID
+/// values into IDs that are valid there.
+///
+/// ```
+/// # use str_id::{export_table, import_table, StrID};
+/// let ids = [StrID::from("x"), StrID::from("y"), StrID::from("x")];
+/// let table = export_table(&ids);
+/// assert_eq!(table.len(), 2);
+///
+/// // later, in some other run...
+/// let remap = import_table(&table);
+/// let restored: Vec<StrID> =
+///   ids.iter().map(|id| remap[&id.as_usize()]).collect();
+/// assert_eq!(restored, ["x", "y", "x"]);
+/// ```
+#[must_use]
+pub fn export_table(ids: &[StrID]) -> Vec<(usize, StaticStr)> {
+  let mut ids = ids.to_vec();
+  ids.sort_unstable();
+  ids.dedup();
+  ids.into_iter().map(|id| (id.as_usize(), id.as_str())).collect()
+}
+
+/// Interns every string of a table from [export_table], giving back a map from
+/// each old ID value to the ID of its string in this run of the program.
+///
+/// Unlike [import_snapshot], this doesn't try to keep the old ID values, so it
+/// can't conflict with what's already in the cache. Like
+/// [intern_all](crate::intern_all), this takes the write lock just once for
+/// the whole table, after every string has been gotten, so the strings can
+/// also be [StrID] values.
+///
+/// ```
+/// # use str_id::{import_table, StrID};
+/// let table = [(7, StrID::from("seven")), (8, StrID::from("eight"))];
+/// let remap = import_table(&table);
+/// assert_eq!(remap[&7], "seven");
+/// ```
+#[track_caller]
+pub fn import_table<S: AsRef<str>>(
+  table: &[(usize, S)],
+) -> BTreeMap<usize, StrID> {
+  let mut remap = BTreeMap::new();
+  let mut old_ids = table.iter().map(|(old, _)| *old);
+  intern_batch(table.iter().map(|(_, s)| s), |id| {
+    if let Some(old) = old_ids.next() {
+      remap.insert(old, id);
+    }
+  });
+  remap
+}
+
+/// An error from [import_snapshot].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SnapshotError {
+  /// The data doesn't start with the snapshot magic bytes.
+  BadMagic,
+  /// The snapshot uses a format version that this crate can't read.
+  UnsupportedVersion(u8),
+  /// The data ended partway through the snapshot.
+  Truncated,
+  /// There's more data after the end of the snapshot.
+  TrailingBytes,
+  /// The checksum doesn't match the rest of the snapshot.
+  BadChecksum,
+  /// One of the strings isn't valid UTF-8.
+  InvalidUtf8,
+  /// One of the IDs can't be a `StrID` value in this build of the crate, or
+  /// is out of order.
+  InvalidId(u64),
+  /// One of the entries conflicts with an entry that's already in the cache,
+  /// or with another entry in the snapshot.
+  IdConflict(u64),
+}
+
+impl fmt::Display for SnapshotError {
+  #[inline]
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::BadMagic => f.write_str("not a StrID snapshot"),
+      Self::UnsupportedVersion(v) => {
+        write!(f, "unsupported snapshot version: {v}")
+      }
+      Self::Truncated => f.write_str("the snapshot is truncated"),
+      Self::TrailingBytes => f.write_str("extra data after the snapshot"),
+      Self::BadChecksum => f.write_str("the snapshot checksum doesn't match"),
+      Self::InvalidUtf8 => f.write_str("a snapshot string isn't valid UTF-8"),
+      Self::InvalidId(id) => write!(f, "invalid ID in the snapshot: {id}"),
+      Self::IdConflict(id) => {
+        write!(f, "the snapshot entry with ID {id} conflicts with the cache")
+      }
+    }
+  }
+}
+
+impl core::error::Error for SnapshotError {}
+
+/// The lookup table for [crc32].
+const CRC_TABLE: [u32; 256] = {
+  let mut table = [0; 256];
+  let mut i = 0;
+  while i < 256 {
+    let mut c = i as u32;
+    let mut bit = 0;
+    while bit < 8 {
+      c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+      bit += 1;
+    }
+    table[i] = c;
+    i += 1;
+  }
+  table
+};
+
+/// Gets the CRC-32 (IEEE) of some bytes.
+#[inline]
+fn crc32(bytes: &[u8]) -> u32 {
+  !bytes.iter().fold(!0, |c, b| {
+    CRC_TABLE[((c ^ u32::from(*b)) & 0xFF) as usize] ^ (c >> 8)
+  })
+}
+
+/// Reads the parts of a snapshot.
+struct Reader<'a> {
+  bytes: &'a [u8],
+}
+impl<'a> Reader<'a> {
+  #[inline]
+  fn take(&mut self, n: usize) -> Result<&'a [u8], SnapshotError> {
+    if self.bytes.len() < n {
+      return Err(SnapshotError::Truncated);
+    }
+    let (head, tail) = self.bytes.split_at(n);
+    self.bytes = tail;
+    Ok(head)
+  }
+
+  #[inline]
+  fn u8(&mut self) -> Result<u8, SnapshotError> {
+    Ok(self.take(1)?[0])
+  }
+
+  #[inline]
+  fn u64(&mut self) -> Result<u64, SnapshotError> {
+    let mut buf = [0_u8; 8];
+    buf.copy_from_slice(self.take(8)?);
+    Ok(u64::from_le_bytes(buf))
+  }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+  use super::*;
+  use crate::{interned_count, reset_cache, tests::lock_cache};
+
+  /// The ID value that the `k`th string of a shard would get.
+  fn id_for(k: usize, s: &str) -> usize {
+    k * cache().len() + shard_of_str(s)
+  }
+
+  /// Writes a snapshot by hand, with the entries just as they're given.
+  fn snapshot_of(entries: &[(usize, &str)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&MAGIC);
+    out.push(VERSION);
+    out.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    for (id, s) in entries {
+      out.extend_from_slice(&(*id as u64).to_le_bytes());
+      out.extend_from_slice(&(s.len() as u64).to_le_bytes());
+      out.extend_from_slice(s.as_bytes());
+    }
+    let checksum = crc32(&out);
+    out.extend_from_slice(&checksum.to_le_bytes());
+    out
+  }
+
+  #[test]
+  fn round_trips_the_whole_cache() {
+    let _lock = lock_cache();
+    reset_cache();
+    let ids = ["snapshot a", "snapshot b", "", "snapshot c"].map(StrID::from);
+    let bytes = export_snapshot();
+    reset_cache();
+    assert_eq!(interned_count(), 0);
+    assert_eq!(import_snapshot(&bytes), Ok(()));
+    assert_eq!(interned_count(), ids.len());
+    for id in ids {
+      assert_eq!(StrID::get(id.as_str()), Some(id));
+      assert_eq!(StrID::try_from_usize(id.as_usize()), Some(id));
+    }
+    // importing it again over itself changes nothing.
+    assert_eq!(import_snapshot(&bytes), Ok(()));
+    assert_eq!(interned_count(), ids.len());
+    assert_eq!(export_snapshot(), bytes);
+  }
+
+  #[test]
+  fn rejects_damaged_snapshots() {
+    let _lock = lock_cache();
+    reset_cache();
+    let good = snapshot_of(&[(id_for(1, "damaged"), "damaged")]);
+
+    let mut bad_magic = good.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(import_snapshot(&bad_magic), Err(SnapshotError::BadMagic));
+
+    let mut bad_version = good.clone();
+    bad_version[MAGIC.len()] = VERSION + 1;
+    assert_eq!(
+      import_snapshot(&bad_version),
+      Err(SnapshotError::UnsupportedVersion(VERSION + 1))
+    );
+
+    assert_eq!(import_snapshot(b"SI"), Err(SnapshotError::Truncated));
+    assert_eq!(
+      import_snapshot(&good[..MAGIC.len()]),
+      Err(SnapshotError::Truncated)
+    );
+    assert_eq!(
+      import_snapshot(&good[..MAGIC.len() + 1]),
+      Err(SnapshotError::Truncated)
+    );
+
+    // cut off anywhere past the header, it's still reported as cut off.
+    for len in MAGIC.len() + 1..good.len() {
+      assert_eq!(import_snapshot(&good[..len]), Err(SnapshotError::Truncated));
+    }
+    let mut trailing = good.clone();
+    trailing.push(0);
+    assert_eq!(import_snapshot(&trailing), Err(SnapshotError::TrailingBytes));
+
+    let mut bad_checksum = good.clone();
+    *bad_checksum.last_mut().unwrap() ^= 1;
+    assert_eq!(import_snapshot(&bad_checksum), Err(SnapshotError::BadChecksum));
+    let mut bad_string = good.clone();
+    let string_at = good.len() - CHECKSUM_LEN - 1;
+    bad_string[string_at] ^= 1;
+    assert_eq!(import_snapshot(&bad_string), Err(SnapshotError::BadChecksum));
+
+    // none of that touched the cache.
+    assert_eq!(interned_count(), 0);
+    assert_eq!(import_snapshot(&good), Ok(()));
+    assert_eq!(interned_count(), 1);
+  }
+
+  #[test]
+  fn rejects_conflicts_with_the_cache() {
+    let _lock = lock_cache();
+    reset_cache();
+    let cached = StrID::from("already cached");
+    let count = interned_count();
+
+    // the string is cached with some other ID.
+    let moved = id_for(cached.as_usize() / cache().len() + 1, "already cached");
+    assert_eq!(
+      import_snapshot(&snapshot_of(&[(moved, "already cached")])),
+      Err(SnapshotError::IdConflict(moved as u64))
+    );
+
+    // the ID is cached for some other string in the same shard.
+    let other = (0..)
+      .map(|i| alloc::format!("not cached {i}"))
+      .find(|s| shard_of_str(s) == shard_of_id(cached))
+      .unwrap();
+    assert_eq!(
+      import_snapshot(&snapshot_of(&[(cached.as_usize(), &other)])),
+      Err(SnapshotError::IdConflict(cached.as_usize() as u64))
+    );
+
+    // a conflict anywhere means that nothing is inserted.
+    let fresh = id_for(1000, "fresh");
+    assert_eq!(
+      import_snapshot(&snapshot_of(&[
+        (moved, "already cached"),
+        (fresh, "fresh")
+      ])),
+      Err(SnapshotError::IdConflict(moved as u64))
+    );
+    assert_eq!(interned_count(), count);
+    assert_eq!(StrID::get("fresh"), None);
+  }
+
+  #[test]
+  fn rejects_a_string_that_is_in_the_snapshot_twice() {
+    let _lock = lock_cache();
+    reset_cache();
+    let first = id_for(1, "twice");
+    let second = id_for(2, "twice");
+    assert_eq!(
+      import_snapshot(&snapshot_of(&[(first, "twice"), (second, "twice")])),
+      Err(SnapshotError::IdConflict(second as u64))
+    );
+    assert_eq!(interned_count(), 0);
+  }
+
+  #[test]
+  fn new_ids_come_after_the_imported_ones() {
+    let _lock = lock_cache();
+    reset_cache();
+    let imported = id_for(500, "imported");
+    assert_eq!(
+      import_snapshot(&snapshot_of(&[(imported, "imported")])),
+      Ok(())
+    );
+    for i in 0..10 {
+      let id = StrID::from(alloc::format!("after the import {i}").as_str());
+      assert!(id.as_usize() > imported);
+    }
+  }
+}